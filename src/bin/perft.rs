@@ -0,0 +1,33 @@
+use std::env;
+use std::process;
+
+use chessmate::chess::engine::BoardState;
+
+const USAGE: &str = "usage: perft <depth> [fen]";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let depth = match args.first().map(|x| x.parse::<u32>()) {
+        Some(Ok(depth)) => depth,
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut board = BoardState::new();
+    if args.len() > 1 {
+        if let Err(err) = board.parse_fen(&args[1..].join(" ")) {
+            eprintln!("invalid fen: {}", err);
+            process::exit(2);
+        }
+    }
+
+    let mut total = 0;
+    for (player_move, count) in board.divide(depth) {
+        println!("{}: {}", player_move, count);
+        total += count;
+    }
+    println!();
+    println!("Nodes searched: {}", total);
+}
@@ -0,0 +1,364 @@
+//! The built-in engine speaking Universal Chess Interface over standard
+//! input and output, for chess GUIs and tournament managers
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chessmate::chess::engine::{
+    BoardState, Color, Limits, Score, Search, SearchInfo,
+};
+
+const NAME: &str = "chessmate";
+const AUTHOR: &str = "chessmate developers";
+
+const DEFAULT_HASH: usize = 16;
+const MAX_HASH: usize = 1024;
+
+// moves to go assumed when GUI doesn't tell
+const MOVES_TO_GO: u64 = 30;
+
+// time kept for communication with GUI
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// Writes line of answer to GUI, like `println!`
+macro_rules! say {
+    ($out:expr, $($arg:tt)*) => {
+        say(&$out, format_args!($($arg)*))
+    };
+}
+
+// output is shared with the thread searching
+fn say<W: Write>(out: &Mutex<W>, line: fmt::Arguments) {
+    if let Ok(mut out) = out.lock() {
+        let _ = writeln!(out, "{}", line);
+        let _ = out.flush();
+    }
+}
+
+/// Search running in background, it returns searcher when done
+struct Thinking {
+    handle: JoinHandle<Search>,
+    stop: Arc<AtomicBool>,
+}
+
+struct Uci<W> {
+    out: Arc<Mutex<W>>,
+    board: BoardState,
+    // `None` while it is thinking
+    search: Option<Search>,
+    thinking: Option<Thinking>,
+    hash: usize,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    fn new(out: Arc<Mutex<W>>) -> Uci<W> {
+        Uci {
+            out,
+            board: BoardState::new(),
+            search: Some(Search::new(DEFAULT_HASH)),
+            thinking: None,
+            hash: DEFAULT_HASH,
+        }
+    }
+
+    /// Handles command, returns `false` if it is time to quit
+    fn command(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("uci") => {
+                say!(self.out, "id name {}", NAME);
+                say!(self.out, "id author {}", AUTHOR);
+                say!(
+                    self.out,
+                    "option name Hash type spin default {} min 1 max {}",
+                    DEFAULT_HASH,
+                    MAX_HASH
+                );
+                say!(self.out, "option name Clear Hash type button");
+                say!(self.out, "uciok");
+            }
+            Some("isready") => say!(self.out, "readyok"),
+            Some("ucinewgame") => {
+                self.search().clear();
+                self.board = BoardState::new();
+            }
+            Some("setoption") => self.set_option(&words.collect::<Vec<_>>()),
+            Some("position") => self.position(&words.collect::<Vec<_>>()),
+            Some("go") => self.go(&words.collect::<Vec<_>>()),
+            Some("stop") => {
+                self.search();
+            }
+            Some("quit") => {
+                self.search();
+                return false;
+            }
+            Some(x) => say!(self.out, "info string unknown command {}", x),
+            None => (),
+        }
+        true
+    }
+
+    /// Stops thinking, if there is any, and takes searcher back
+    fn search(&mut self) -> &mut Search {
+        if let Some(thinking) = self.thinking.take() {
+            thinking.stop.store(true, Ordering::Relaxed);
+            if let Ok(search) = thinking.handle.join() {
+                self.search = Some(search);
+            }
+        }
+        let hash = self.hash;
+        self.search.get_or_insert_with(|| Search::new(hash))
+    }
+
+    /// `setoption name <name> [value <value>]`, name may have spaces
+    fn set_option(&mut self, words: &[&str]) {
+        let (name, value) = match words.iter().position(|x| *x == "value") {
+            Some(i) => (&words[..i], Some(words[i + 1..].join(" "))),
+            None => (words, None),
+        };
+        let name = match name.split_first() {
+            Some((&"name", name)) => name.join(" "),
+            _ => return say!(self.out, "info string invalid option"),
+        };
+        match (name.to_lowercase().as_str(), value) {
+            ("hash", Some(value)) => match value.parse::<usize>() {
+                Ok(hash) if (1..=MAX_HASH).contains(&hash) => {
+                    self.search();
+                    self.hash = hash;
+                    self.search = Some(Search::new(hash));
+                }
+                _ => say!(self.out, "info string invalid hash size {}", value),
+            },
+            ("clear hash", _) => self.search().clear(),
+            _ => say!(self.out, "info string unknown option {}", name),
+        }
+    }
+
+    /// `position (startpos | fen <fen>) [moves <move>...]`
+    fn position(&mut self, words: &[&str]) {
+        self.search();
+        let moves_at = words.iter().position(|x| *x == "moves");
+        let position = &words[..moves_at.unwrap_or(words.len())];
+        let mut board = BoardState::new();
+        match position.split_first() {
+            Some((&"startpos", [])) => (),
+            Some((&"fen", fen)) => {
+                if let Err(err) = board.parse_fen(&fen.join(" ")) {
+                    say!(self.out, "info string invalid fen: {}", err);
+                    return;
+                }
+            }
+            _ => {
+                say!(self.out, "info string invalid position");
+                return;
+            }
+        }
+        for x in moves_at.map_or(&[][..], |i| &words[i + 1..]) {
+            match board.parse_uci(x) {
+                Ok(x) if board.move_list().contains(&x) => {
+                    board.make_move(x);
+                }
+                _ => {
+                    say!(self.out, "info string illegal move {}", x);
+                    break;
+                }
+            }
+        }
+        self.board = board;
+    }
+
+    /// `go [depth <plies>] [movetime <ms>] [wtime <ms>] [btime <ms>]
+    /// [winc <ms>] [binc <ms>] [movestogo <moves>] [infinite]`
+    fn go(&mut self, words: &[&str]) {
+        self.search();
+        let hash = self.hash;
+        let mut search =
+            self.search.take().unwrap_or_else(|| Search::new(hash));
+        let limits = limits(words, self.board.turn());
+        let infinite = words.contains(&"infinite");
+        let stop = search.stop_flag();
+        stop.store(false, Ordering::Relaxed);
+        let board = self.board.clone();
+        let stopped = stop.clone();
+        let out = self.out.clone();
+        let handle = thread::spawn(move || {
+            let best = search.run(&board, limits, |x| print_info(&out, x));
+            // GUI expects no answer for infinite search until it stops it
+            while infinite && !stopped.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(10));
+            }
+            match best {
+                Some(x) => say!(out, "bestmove {}", x),
+                None => say!(out, "bestmove 0000"),
+            }
+            search
+        });
+        self.thinking = Some(Thinking { handle, stop });
+    }
+}
+
+/// Limits of search from `go` arguments, clock is split evenly between
+/// the remaining moves
+fn limits(words: &[&str], turn: Color) -> Limits {
+    let millis = |name| argument::<u64>(words, name);
+    let (time, increment) = match turn {
+        Color::White => (millis("wtime"), millis("winc")),
+        Color::Black => (millis("btime"), millis("binc")),
+    };
+    let moves_to_go = argument(words, "movestogo").unwrap_or(MOVES_TO_GO);
+    let clock = time.map(|time| {
+        let left = Duration::from_millis(time).saturating_sub(MOVE_OVERHEAD);
+        let share = Duration::from_millis(
+            time / moves_to_go.max(1) + increment.unwrap_or(0) * 3 / 4,
+        );
+        share.min(left)
+    });
+    let move_time = millis("movetime")
+        .map(|x| Duration::from_millis(x).saturating_sub(MOVE_OVERHEAD));
+    Limits {
+        depth: argument(words, "depth"),
+        time: move_time.or(clock),
+    }
+}
+
+/// Value following `name`, `None` if it is missing or out of range
+fn argument<T: FromStr>(words: &[&str], name: &str) -> Option<T> {
+    words
+        .iter()
+        .position(|x| *x == name)
+        .and_then(|i| words.get(i + 1))
+        .and_then(|x| x.parse().ok())
+}
+
+fn print_info<W: Write>(out: &Mutex<W>, info: &SearchInfo) {
+    let score = match info.score {
+        Score::Centipawns(x) => format!("cp {}", x),
+        Score::Mate(x) => format!("mate {}", x),
+    };
+    let millis = info.time.as_millis();
+    let pv: Vec<_> = info.pv.iter().map(|x| x.to_string()).collect();
+    say!(
+        out,
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        info.depth,
+        score,
+        info.nodes,
+        info.nodes as u128 * 1000 / millis.max(1),
+        millis,
+        pv.join(" ")
+    );
+}
+
+/// Handles commands read from `input` until `quit` or the end of input,
+/// answers are written to `output`
+fn run<R, W>(input: R, output: Arc<Mutex<W>>)
+where
+    R: BufRead,
+    W: Write + Send + 'static,
+{
+    let mut uci = Uci::new(output);
+    for line in input.lines() {
+        match line {
+            Ok(line) if uci.command(&line) => (),
+            _ => break,
+        }
+    }
+    uci.search();
+}
+
+fn main() {
+    run(io::stdin().lock(), Arc::new(Mutex::new(io::stdout())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uci() -> Uci<Vec<u8>> {
+        Uci::new(Arc::new(Mutex::new(Vec::new())))
+    }
+
+    fn output(uci: &Uci<Vec<u8>>) -> String {
+        String::from_utf8(uci.out.lock().unwrap().clone()).unwrap()
+    }
+
+    #[test]
+    fn test_position() {
+        let mut uci = uci();
+        assert!(uci.command("position startpos moves e2e4 e7e5 g1f3"));
+        assert_eq!(
+            uci.board.export_to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        let fen = "8/8/8/4k3/8/8/4P3/4K3 w - - 0 1";
+        uci.command(&format!("position fen {} moves e2e4 e5e4", fen));
+        assert_eq!(uci.board.export_to_fen(), "8/8/8/8/4k3/8/8/4K3 w - - 0 2");
+        // moves are made up to the illegal one
+        uci.command("position startpos moves e2e4 e2e4");
+        assert_eq!(uci.board.turn(), Color::Black);
+        uci.command("position fen x");
+        assert_eq!(uci.board.turn(), Color::Black);
+        assert_eq!(
+            output(&uci),
+            "info string illegal move e2e4\n\
+             info string invalid fen: expected 6 fields, found 1\n"
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let words: Vec<_> =
+            "wtime 60000 btime 30000 winc 1000 binc 0 movestogo 10"
+                .split_whitespace()
+                .collect();
+        let limits = limits(&words, Color::White);
+        assert_eq!(limits.time, Some(Duration::from_millis(6750)));
+        assert_eq!(limits.depth, None);
+        let limits = super::limits(&words, Color::Black);
+        assert_eq!(limits.time, Some(Duration::from_millis(3000)));
+        let limits = super::limits(&["movetime", "1000"], Color::White);
+        assert_eq!(limits.time, Some(Duration::from_millis(950)));
+        let limits = super::limits(&["depth", "5"], Color::White);
+        assert_eq!(limits.depth, Some(5));
+        assert_eq!(limits.time, None);
+        let limits = super::limits(&["depth", "4294967301"], Color::White);
+        assert_eq!(limits.depth, None);
+    }
+
+    #[test]
+    fn test_set_option() {
+        let mut uci = uci();
+        uci.command("setoption name Hash value 32");
+        assert_eq!(uci.hash, 32);
+        uci.command("setoption name Hash value 0");
+        uci.command("setoption name Clear Hash");
+        uci.command("setoption name Contempt value 10");
+        uci.command("setoption Hash");
+        assert_eq!(uci.hash, 32);
+        assert_eq!(
+            output(&uci),
+            "info string invalid hash size 0\n\
+             info string unknown option Contempt\n\
+             info string invalid option\n"
+        );
+    }
+
+    #[test]
+    fn test_run() {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let input = "uci\nisready\nposition startpos\ngo depth 1\nquit\n";
+        run(input.as_bytes(), output.clone());
+        let output = String::from_utf8(output.lock().unwrap().clone());
+        let output = output.unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines[0], "id name chessmate");
+        assert!(lines.contains(&"uciok"));
+        assert!(lines.contains(&"readyok"));
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+    }
+}
@@ -0,0 +1,250 @@
+//! Players driven by engines instead of people, they wait in the lobby
+//! like any other player and play through the same game server
+
+use std::marker::{PhantomData, Unpin};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use actix::{
+    fut, Actor, ActorContext, ActorFuture, Addr, AsyncContext, Context,
+    ContextFutureSpawner, Handler, WrapFuture,
+};
+use actix_web::web;
+
+use crate::core::UserId;
+use crate::domain::{CancelReason, Game, PlayerCommand};
+use crate::observers::{
+    Connect, Disconnect, FindPair, GameEvent, NewGame, PlayerAction,
+    TicketCancelled,
+};
+use crate::runtime::DefaultGameServer;
+
+/// Users with ids starting from this one are bots, people can't take them
+pub const BOT_USER_IDS: UserId = 1 << 63;
+
+static NEXT_BOT_ID: AtomicU64 = AtomicU64::new(BOT_USER_IDS);
+
+/// Gives unique id for new bot
+pub fn next_bot_id() -> UserId {
+    NEXT_BOT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Engine following single game from its start
+pub trait Engine<G: Game>: Unpin + Send + 'static {
+    /// Applies action of either side, in canonical form
+    fn apply(&mut self, action: &G::Action);
+
+    /// Side which should act now
+    fn to_move(&self) -> G::Side;
+
+    /// Chooses action for side to move, may take long time, so it is
+    /// run on thread pool
+    /// # Returns
+    /// * `None` - if there are no actions, e.g. game is over
+    fn think(&mut self) -> Option<G::Action>;
+
+    /// Tells state of clocks before thinking in timed game, so engine can
+    /// spend its time wisely
+    fn clock(&mut self, _side: G::Side, _clock: &G::Clock) {}
+}
+
+/// Bot waiting for game with given wish. When game is found, another bot
+/// with the same wish takes its place, so the lobby is never left
+/// without one
+pub struct BotPlayer<G: Game, E: Engine<G>> {
+    server: Addr<DefaultGameServer<G>>,
+    user_id: UserId,
+    wish: G::Wish,
+    new_engine: Rc<dyn Fn() -> E>,
+    // engine of the game in progress, taken away while it thinks
+    engine: Option<E>,
+    // side of the bot, known once the game starts
+    side: Option<G::Side>,
+    // last clocks seen, given to engine when it thinks next time
+    clock: Option<G::Clock>,
+    game: PhantomData<G>,
+}
+
+impl<G: Game, E: Engine<G>> BotPlayer<G, E> {
+    /// Starts bot which creates new engine for every game
+    pub fn spawn<F>(
+        server: Addr<DefaultGameServer<G>>,
+        wish: G::Wish,
+        new_engine: F,
+    ) -> Addr<BotPlayer<G, E>>
+    where
+        F: Fn() -> E + 'static,
+    {
+        BotPlayer::respawn(server, wish, Rc::new(new_engine))
+    }
+
+    fn respawn(
+        server: Addr<DefaultGameServer<G>>,
+        wish: G::Wish,
+        new_engine: Rc<dyn Fn() -> E>,
+    ) -> Addr<BotPlayer<G, E>> {
+        BotPlayer {
+            server,
+            user_id: next_bot_id(),
+            wish,
+            new_engine,
+            engine: None,
+            side: None,
+            clock: None,
+            game: PhantomData,
+        }
+        .start()
+    }
+
+    /// Thinks and acts, if it is turn of the bot
+    fn act(&mut self, ctx: &mut Context<Self>) {
+        let (mut engine, side) = match (self.engine.take(), self.side) {
+            (Some(engine), Some(side)) if engine.to_move() == side => {
+                (engine, side)
+            }
+            (engine, _) => {
+                self.engine = engine;
+                return;
+            }
+        };
+        if let Some(clock) = self.clock.take() {
+            engine.clock(side, &clock);
+        }
+        web::block(move || {
+            let action = engine.think();
+            Ok::<_, ()>((engine, action))
+        })
+        .into_actor(self)
+        .then(|res, act, ctx| {
+            match res {
+                Ok((mut engine, Some(action))) => {
+                    engine.apply(&action);
+                    act.engine = Some(engine);
+                    act.command(PlayerCommand::Act(action), ctx);
+                }
+                Ok((_, None)) | Err(_) => {
+                    log::warn!("Bot {} has no move", act.user_id);
+                    act.command(PlayerCommand::Resign, ctx);
+                }
+            }
+            fut::ready(())
+        })
+        .spawn(ctx);
+    }
+
+    /// Waits in the lobby for a game
+    fn find_pair(&mut self, ctx: &mut Context<Self>) {
+        self.server
+            .send(FindPair {
+                user_id: self.user_id,
+                wish: self.wish.clone(),
+                addr: ctx.address().recipient(),
+                cancel_addr: ctx.address().recipient(),
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                if !matches!(res, Ok(Ok(()))) {
+                    log::warn!("Bot {} can't wait for game", act.user_id);
+                    ctx.stop();
+                }
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    fn command(
+        &mut self,
+        command: PlayerCommand<G::Action>,
+        ctx: &mut Context<Self>,
+    ) {
+        self.server
+            .send(PlayerAction {
+                user_id: self.user_id,
+                command,
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(Ok(_)) => (),
+                    Ok(Err(err)) => {
+                        log::warn!("Bot {} can't act: {:?}", act.user_id, err);
+                        ctx.stop();
+                    }
+                    Err(_) => ctx.stop(),
+                }
+                fut::ready(())
+            })
+            .spawn(ctx);
+    }
+}
+
+impl<G: Game, E: Engine<G>> Actor for BotPlayer<G, E> {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.server.do_send(Connect {
+            user_id: self.user_id,
+            addr: ctx.address().recipient(),
+        });
+        self.find_pair(ctx);
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        self.server.do_send(Disconnect {
+            user_id: self.user_id,
+            addr: ctx.address().recipient(),
+        });
+    }
+}
+
+impl<G: Game, E: Engine<G>> Handler<TicketCancelled> for BotPlayer<G, E> {
+    type Result = ();
+
+    /// Bot waits for people as long as server works
+    fn handle(&mut self, msg: TicketCancelled, ctx: &mut Context<Self>) {
+        match msg.0 {
+            CancelReason::Expired => self.find_pair(ctx),
+            _ => ctx.stop(),
+        }
+    }
+}
+
+impl<G: Game, E: Engine<G>> Handler<NewGame> for BotPlayer<G, E> {
+    type Result = ();
+
+    fn handle(&mut self, msg: NewGame, ctx: &mut Context<Self>) {
+        log::info!("Bot {} plays game {}", self.user_id, msg.0);
+        BotPlayer::respawn(
+            self.server.clone(),
+            self.wish.clone(),
+            self.new_engine.clone(),
+        );
+        self.engine = Some((self.new_engine)());
+        self.act(ctx);
+    }
+}
+
+impl<G: Game, E: Engine<G>> Handler<GameEvent<G>> for BotPlayer<G, E> {
+    type Result = ();
+
+    fn handle(&mut self, msg: GameEvent<G>, ctx: &mut Context<Self>) {
+        match msg {
+            GameEvent::Opponent(PlayerCommand::Act(action)) => {
+                if let Some(engine) = self.engine.as_mut() {
+                    engine.apply(&action);
+                }
+                self.act(ctx);
+            }
+            GameEvent::Opponent(PlayerCommand::OfferDraw) => {
+                self.command(PlayerCommand::DeclineDraw, ctx)
+            }
+            GameEvent::Side(side) => {
+                self.side = Some(side);
+                self.act(ctx);
+            }
+            GameEvent::Clock(clock) => self.clock = Some(clock),
+            GameEvent::Opponent(_) => (),
+            GameEvent::GameOver(_) => ctx.stop(),
+        }
+    }
+}
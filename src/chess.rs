@@ -1,17 +1,348 @@
+pub mod clock;
+pub mod computer;
+pub mod engine;
+pub mod pgn;
+pub mod uci;
+
+use crate::core::UserId;
 use crate::domain::{Game, Id, Wish};
-use std::ops::Not;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
 use std::str::FromStr;
+use std::time::{Instant, SystemTime};
+
+use engine::{
+    BoardState, Castling, Move, ParseSanError, ParseUciError, Promotion, San,
+    Square,
+};
+
+pub use engine::{Color, InvalidMove, Outcome, Termination};
+
+use clock::{Category, Clock, ClockState, TimeControl};
+use computer::Level;
+use pgn::{Pgn, PgnMove};
+
+pub struct ChessGame {
+    board: BoardState,
+    record: Pgn,
+    clock: Option<Clock>,
+}
+
+impl ChessGame {
+    /// Game with clocks of given time control, started right away
+    fn with_clock(control: Option<TimeControl>) -> ChessGame {
+        let mut record = Pgn::new();
+        record.set_tag("Event", "Casual game");
+        record.set_tag("Site", "chessmate");
+        record.set_tag("Date", pgn::pgn_date(SystemTime::now()));
+        record.set_tag("Round", "-");
+        let tag = control.map_or("-".to_string(), |x| x.pgn_tag());
+        record.set_tag("TimeControl", tag);
+        ChessGame {
+            board: BoardState::new(),
+            record,
+            clock: control.map(|x| Clock::new(x, Instant::now())),
+        }
+    }
 
-pub struct ChessGame;
+    /// Applies action of player at given moment
+    fn apply_at(
+        &mut self,
+        side: Color,
+        action: ChessAction,
+        now: Instant,
+    ) -> Result<(ChessAction, Option<Outcome>), DoActionError> {
+        if side != self.board.turn() {
+            return Err(DoActionError::InvalidMove(InvalidMove::NotYourTurn));
+        }
+        if self.clock.as_ref().is_some_and(|x| x.is_flagged(now)) {
+            return Err(DoActionError::OutOfTime);
+        }
+        let game_move = match action {
+            ChessAction::Move(from, to) => self.board.uci_move(from, to, None),
+            ChessAction::Promotion(from, to, figure) => {
+                self.board.uci_move(from, to, Some(figure))
+            }
+            ChessAction::CastleShort => Move::Castling(match side {
+                Color::White => Castling::WK,
+                Color::Black => Castling::BK,
+            }),
+            ChessAction::CastleLong => Move::Castling(match side {
+                Color::White => Castling::WQ,
+                Color::Black => Castling::BQ,
+            }),
+            ChessAction::San(san) => {
+                self.board.find_san(san).map_err(|err| match err {
+                    ParseSanError::AmbiguousMove => {
+                        DoActionError::AmbiguousMove
+                    }
+                    _ => {
+                        DoActionError::InvalidMove(InvalidMove::IllegalPattern)
+                    }
+                })?
+            }
+        };
+        let san = self.board.to_san(game_move);
+        let outcome = self
+            .board
+            .validate_move(game_move, side)
+            .map_err(DoActionError::InvalidMove)?;
+        let clock = self.clock.as_mut().map(|x| x.press(now));
+        if let Some(san) = san {
+            log::debug!("{:?} plays {}", side, san);
+            self.record.moves.push(PgnMove { san, clock });
+        }
+        let outcome = outcome.map(|x| self.finish(x));
+        Ok((ChessAction::from_move(game_move), outcome))
+    }
 
-// impl TttGame {
-// fn new() -> TttGame {
-// TttGame {}
-// }
-// }
+    /// Player out of time loses, unless the opponent can't checkmate
+    fn check_time_at(&mut self, now: Instant) -> Option<Outcome> {
+        if !self.clock.as_ref()?.is_flagged(now) {
+            return None;
+        }
+        let winner = !self.board.turn();
+        let outcome = if self.board.can_checkmate(winner) {
+            Outcome::win(winner, Termination::Timeout)
+        } else {
+            Outcome::draw(Termination::Timeout)
+        };
+        Some(self.finish(outcome))
+    }
+
+    /// Writes result of the game to the record
+    fn finish(&mut self, outcome: Outcome) -> Outcome {
+        self.record.set_result(outcome.to_string());
+        let termination = match outcome.termination {
+            Termination::Timeout => "time forfeit",
+            Termination::Abandonment | Termination::Aborted => "abandoned",
+            _ => "normal",
+        };
+        self.record.set_tag("Termination", termination);
+        outcome
+    }
+}
 
 impl Game for ChessGame {
     type Wish = ChessWish;
+    type Side = Color;
+    type Action = ChessAction;
+    type Outcome = Outcome;
+    type ActionError = DoActionError;
+    type Clock = ClockState;
+    type Category = Category;
+
+    /// Time control of bot's opponent is taken, bots play any
+    fn new(wishes: [&ChessWish; 2]) -> ChessGame {
+        let [first, second] = wishes;
+        ChessGame::with_clock(first.time_control.or(second.time_control))
+    }
+
+    fn category(&self) -> Category {
+        Category::of(self.clock.as_ref().map(Clock::control))
+    }
+
+    fn score(outcome: &Outcome, side: Color) -> Option<f64> {
+        match outcome.winner {
+            _ if outcome.termination == Termination::Aborted => None,
+            Some(winner) if winner == side => Some(1.0),
+            Some(_) => Some(0.0),
+            None => Some(0.5),
+        }
+    }
+
+    fn state(&self) -> String {
+        self.board.export_to_fen()
+    }
+
+    fn apply(
+        &mut self,
+        side: Color,
+        action: ChessAction,
+    ) -> Result<(ChessAction, Option<Outcome>), DoActionError> {
+        self.apply_at(side, action, Instant::now())
+    }
+
+    fn resign(&mut self, side: Color) -> Outcome {
+        self.finish(Outcome::win(!side, Termination::Resignation))
+    }
+
+    fn abandon(&mut self, side: Color) -> Outcome {
+        if self.board.plies() < 2 {
+            self.finish(Outcome::draw(Termination::Aborted))
+        } else {
+            self.finish(Outcome::win(!side, Termination::Abandonment))
+        }
+    }
+
+    fn draw(&mut self) -> Outcome {
+        self.finish(Outcome::draw(Termination::Agreement))
+    }
+
+    fn claim_draw(&mut self, _side: Color) -> Result<Outcome, DoActionError> {
+        self.board
+            .draw_claim()
+            .map(|x| self.finish(Outcome::draw(x)))
+            .ok_or(DoActionError::NoDrawClaim)
+    }
+
+    fn clock(&self) -> Option<ClockState> {
+        self.clock.as_ref().map(|x| x.state(Instant::now()))
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.clock.as_ref().and_then(Clock::deadline)
+    }
+
+    fn check_time(&mut self) -> Option<Outcome> {
+        self.check_time_at(Instant::now())
+    }
+
+    fn replay(&self, users: &[(UserId, Color); 2]) -> String {
+        let mut record = self.record.clone();
+        for (user, side) in users.iter() {
+            let tag = match side {
+                Color::White => "White",
+                Color::Black => "Black",
+            };
+            record.set_tag(tag, user.to_string());
+        }
+        record.to_string()
+    }
+}
+
+/// Action player can perform in chess game, canonical form of any move
+/// is UCI long algebraic notation
+/// * `Move` - move figure from one square to another, e.g. `e2e4`, or
+///   castle moving king by two squares, e.g. `e1g1`
+/// * `Promotion` - move pawn to the last row and promote it, e.g. `e7e8q`
+/// * `CastleShort` - `O-O`
+/// * `CastleLong` - `O-O-O`
+/// * `San` - any move in Standard Algebraic Notation, e.g. `Nbd7`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChessAction {
+    Move(Square, Square),
+    Promotion(Square, Square, Promotion),
+    CastleShort,
+    CastleLong,
+    San(San),
+}
+
+#[derive(Debug)]
+pub enum ChessActionErr {
+    InvalidAction,
+}
+
+impl FromStr for ChessAction {
+    type Err = ChessActionErr;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "O-O" | "0-0" => Ok(ChessAction::CastleShort),
+            "O-O-O" | "0-0-0" => Ok(ChessAction::CastleLong),
+            _ if s.as_bytes().first().is_some_and(u8::is_ascii_lowercase) => {
+                match engine::convert_uci_to_squares(s) {
+                    Ok((from, to, None)) => Ok(ChessAction::Move(from, to)),
+                    Ok((from, to, Some(figure))) => {
+                        Ok(ChessAction::Promotion(from, to, figure))
+                    }
+                    Err(ParseUciError::InvalidPromotion) => {
+                        Err(ChessActionErr::InvalidAction)
+                    }
+                    Err(ParseUciError::InvalidSquare) => {
+                        ChessAction::parse_san(s)
+                    }
+                }
+            }
+            _ => ChessAction::parse_san(s),
+        }
+    }
+}
+
+impl ChessAction {
+    /// Canonical form of the move, UCI long algebraic notation
+    fn from_move(game_move: Move) -> ChessAction {
+        match game_move {
+            Move::Move(from, to) => ChessAction::Move(from, to),
+            Move::Promotion(from, to, figure) => {
+                ChessAction::Promotion(from, to, figure)
+            }
+            Move::Castling(castling) => {
+                let (from, to) = castling.king_squares();
+                ChessAction::Move(from, to)
+            }
+            Move::Surrender(_) => unreachable!(),
+        }
+    }
+
+    fn parse_san(s: &str) -> Result<ChessAction, ChessActionErr> {
+        s.parse()
+            .map(ChessAction::San)
+            .map_err(|_| ChessActionErr::InvalidAction)
+    }
+}
+
+impl Display for ChessAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChessAction::Move(from, to) => {
+                let from = engine::convert_to_text_notation(*from)
+                    .map_err(|_| fmt::Error)?;
+                let to = engine::convert_to_text_notation(*to)
+                    .map_err(|_| fmt::Error)?;
+                write!(f, "{}{}", from, to)
+            }
+            ChessAction::Promotion(from, to, figure) => {
+                write!(f, "{}", ChessAction::Move(*from, *to))?;
+                match figure {
+                    Promotion::Queen => write!(f, "q"),
+                    Promotion::Rook => write!(f, "r"),
+                    Promotion::Bishop => write!(f, "b"),
+                    Promotion::Knight => write!(f, "n"),
+                }
+            }
+            ChessAction::CastleShort => write!(f, "O-O"),
+            ChessAction::CastleLong => write!(f, "O-O-O"),
+            ChessAction::San(san) => write!(f, "{}", san),
+        }
+    }
+}
+
+/// Reason chess action is rejected
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DoActionError {
+    InvalidMove(InvalidMove),
+    /// Move in SAN matches several moves
+    AmbiguousMove,
+    /// Neither threefold repetition nor fifty-move rule applies
+    NoDrawClaim,
+    /// Player moves after the flag fell
+    OutOfTime,
+}
+
+impl Display for DoActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DoActionError::InvalidMove(InvalidMove::NotYourTurn) => {
+                write!(f, "it is not your turn")
+            }
+            DoActionError::InvalidMove(InvalidMove::PiecePinned) => {
+                write!(f, "piece is pinned to the king")
+            }
+            DoActionError::InvalidMove(InvalidMove::LeavesKingInCheck) => {
+                write!(f, "move leaves king in check")
+            }
+            DoActionError::InvalidMove(InvalidMove::IllegalPattern) => {
+                write!(f, "figure can't move this way")
+            }
+            DoActionError::AmbiguousMove => {
+                write!(f, "several figures can make this move")
+            }
+            DoActionError::NoDrawClaim => write!(f, "draw can't be claimed"),
+            DoActionError::OutOfTime => write!(f, "your time is out"),
+        }
+    }
 }
 
 impl Id for u64 {
@@ -23,45 +354,457 @@ impl Id for u64 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Color {
-    Black,
-    White,
+/// Engine playing instead of person
+/// * `Computer` - built-in engine of given level
+/// * `Uci` - external UCI engine with given name
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Bot {
+    Computer(Level),
+    Uci(String),
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Color player wants to play, who with and how long
+/// * `color` - `None` if player is fine with either color
+/// * `opponent` - bot wanted as opponent, `None` to play with people
+/// * `engine` - bot making the wish, `None` for people
+/// * `time_control` - clocks of the game, `None` for game without them
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ChessWish {
-    color: Color,
+    color: Option<Color>,
+    opponent: Option<Bot>,
+    engine: Option<Bot>,
+    time_control: Option<TimeControl>,
 }
 
-impl Not for Color {
-    type Output = Color;
-
-    fn not(self) -> Self::Output {
-        match self {
-            Color::Black => Color::White,
-            Color::White => Color::Black,
+impl ChessWish {
+    /// Wish of bot waiting for people who want to play with it
+    pub fn bot(color: Color, bot: Bot) -> ChessWish {
+        ChessWish {
+            color: Some(color),
+            opponent: None,
+            engine: Some(bot),
+            time_control: None,
         }
     }
 }
 
+#[derive(Debug)]
 pub enum ChessWishErr {
     InvalidWish,
 }
 
 impl Wish for ChessWish {
+    type Side = Color;
+    type Category = Category;
+    type Bucket = ChessWish;
+
+    /// Players want different colors and the same time control, or no
+    /// clocks at all, bots accept any time control
     fn is_match(&self, other: &ChessWish) -> bool {
-        self.color != other.color
+        let colors = self.color.is_none() || self.color != other.color;
+        let time_control = self.time_control == other.time_control
+            || self.engine.is_some()
+            || other.engine.is_some();
+        colors
+            && time_control
+            && self.opponent == other.engine
+            && other.opponent == self.engine
+    }
+
+    /// Wishes are grouped by themselves, except time control of games
+    /// with bots, which is taken from the person
+    fn bucket(&self) -> ChessWish {
+        let mut bucket = self.clone();
+        if self.opponent.is_some() || self.engine.is_some() {
+            bucket.time_control = None;
+        }
+        bucket
+    }
+
+    fn matching_buckets(&self) -> Vec<ChessWish> {
+        let colors = match self.color {
+            Some(color) => vec![Some(!color), None],
+            None => vec![Some(Color::White), Some(Color::Black), None],
+        };
+        colors
+            .into_iter()
+            .map(|color| {
+                ChessWish {
+                    color,
+                    opponent: self.engine.clone(),
+                    engine: self.opponent.clone(),
+                    time_control: self.time_control,
+                }
+                .bucket()
+            })
+            .collect()
+    }
+
+    fn category(&self) -> Category {
+        Category::of(self.time_control)
+    }
+
+    /// Player who wants particular color gets it. If neither cares, the
+    /// one who had white more often lately gets black, then the one who
+    /// had white last time, otherwise colors are drawn
+    fn sides(&self, other: &ChessWish, history: [&[Color]; 2]) -> [Color; 2] {
+        let whites = |history: &[Color]| -> i32 {
+            history
+                .iter()
+                .map(|x| match x {
+                    Color::White => 1,
+                    Color::Black => -1,
+                })
+                .sum()
+        };
+        let [first, second] = history;
+        let color = match (self.color, other.color) {
+            (Some(color), _) => color,
+            (None, Some(color)) => !color,
+            (None, None) => match (
+                whites(first).cmp(&whites(second)),
+                first.last(),
+                second.last(),
+            ) {
+                (Ordering::Less, _, _) => Color::White,
+                (Ordering::Greater, _, _) => Color::Black,
+                (_, Some(x), Some(y)) if x != y => !*x,
+                _ if rand::random() => Color::White,
+                _ => Color::Black,
+            },
+        };
+        [color, !color]
     }
 }
 
+/// Parses color, or `any` or `random` for either of them, optionally
+/// followed by bot opponent and time control, e.g. `white`,
+/// `black computer`, `any 3+2`, `white computer:5 3+2` or
+/// `white uci:stockfish`
 impl FromStr for ChessWish {
     type Err = ChessWishErr;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "white" => Ok(ChessWish { color: Color::White }),
-            "black" => Ok(ChessWish { color: Color::Black }),
-            _ => Err(ChessWishErr::InvalidWish),
+        let mut words = s.split(' ');
+        let color = match words.next() {
+            Some("white") => Some(Color::White),
+            Some("black") => Some(Color::Black),
+            Some("any") | Some("random") => None,
+            _ => return Err(ChessWishErr::InvalidWish),
+        };
+        let mut wish = ChessWish {
+            color,
+            opponent: None,
+            engine: None,
+            time_control: None,
+        };
+        for word in words {
+            let opponent = match word {
+                "computer" => Some(Bot::Computer(Level::DEFAULT)),
+                x if x.starts_with("computer:") => Some(Bot::Computer(
+                    x["computer:".len()..]
+                        .parse()
+                        .map_err(|_| ChessWishErr::InvalidWish)?,
+                )),
+                x if x.starts_with("uci:") && x.len() > "uci:".len() => {
+                    Some(Bot::Uci(x["uci:".len()..].to_string()))
+                }
+                _ => None,
+            };
+            match (opponent, word.parse()) {
+                (Some(x), _) if wish.opponent.is_none() => {
+                    wish.opponent = Some(x)
+                }
+                (None, Ok(x)) if wish.time_control.is_none() => {
+                    wish.time_control = Some(x)
+                }
+                _ => return Err(ChessWishErr::InvalidWish),
+            }
         }
+        Ok(wish)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn act(
+        game: &mut ChessGame,
+        side: Color,
+        action: &str,
+    ) -> Result<Option<Outcome>, DoActionError> {
+        game.apply(side, action.parse().unwrap())
+            .map(|(_, outcome)| outcome)
+    }
+
+    #[test]
+    fn test_parse_action() {
+        assert_eq!("e2e4".parse::<ChessAction>().unwrap().to_string(), "e2e4");
+        assert_eq!(
+            "O-O-O".parse::<ChessAction>().unwrap(),
+            ChessAction::CastleLong
+        );
+        assert_eq!(
+            "e7e8n".parse::<ChessAction>().unwrap().to_string(),
+            "e7e8n"
+        );
+        assert!("e7e8k".parse::<ChessAction>().is_err());
+        assert!("e".parse::<ChessAction>().is_err());
+        assert!("z2e4".parse::<ChessAction>().is_err());
+        assert_eq!("Nbd7".parse::<ChessAction>().unwrap().to_string(), "Nbd7");
+        assert!(matches!(
+            "exd6 e.p.".parse::<ChessAction>(),
+            Ok(ChessAction::San(_))
+        ));
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut game = ChessGame::with_clock(None);
+        assert_eq!(
+            act(&mut game, Color::Black, "e7e5"),
+            Err(DoActionError::InvalidMove(InvalidMove::NotYourTurn))
+        );
+        assert_eq!(
+            act(&mut game, Color::White, "e2e5"),
+            Err(DoActionError::InvalidMove(InvalidMove::IllegalPattern))
+        );
+        assert_eq!(
+            game.abandon(Color::White).termination,
+            Termination::Aborted
+        );
+        assert_eq!(act(&mut game, Color::White, "e2e4"), Ok(None));
+        assert_eq!(act(&mut game, Color::Black, "e7e5"), Ok(None));
+        assert_eq!(
+            act(&mut game, Color::White, "Ke3"),
+            Err(DoActionError::InvalidMove(InvalidMove::IllegalPattern))
+        );
+        assert_eq!(act(&mut game, Color::White, "Nf3"), Ok(None));
+        assert_eq!(
+            game.abandon(Color::White),
+            Outcome::win(Color::Black, Termination::Abandonment)
+        );
+        assert_eq!(
+            game.resign(Color::Black),
+            Outcome::win(Color::White, Termination::Resignation)
+        );
+    }
+
+    #[test]
+    fn test_canonical_action() {
+        let mut game = ChessGame::with_clock(None);
+        let applied = |game: &mut ChessGame, side, action: &str| {
+            let (action, _) =
+                game.apply(side, action.parse().unwrap()).unwrap();
+            action.to_string()
+        };
+        assert_eq!(applied(&mut game, Color::White, "Nf3"), "g1f3");
+        game.board
+            .parse_fen("r3k2r/6P1/8/8/8/8/8/R3K2R w KQkq - 0 1")
+            .unwrap();
+        assert_eq!(applied(&mut game, Color::White, "e1g1"), "e1g1");
+        assert_eq!(game.state(), "r3k2r/6P1/8/8/8/8/8/R4RK1 b kq - 1 1");
+        assert_eq!(applied(&mut game, Color::Black, "O-O-O"), "e8c8");
+        assert_eq!(applied(&mut game, Color::White, "g7h8n"), "g7h8n");
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut game = ChessGame::with_clock(None);
+        for (side, action) in [
+            (Color::White, "f2f3"),
+            (Color::Black, "e5"),
+            (Color::White, "g4"),
+            (Color::Black, "d8h4"),
+        ]
+        .iter()
+        {
+            act(&mut game, *side, action).unwrap();
+        }
+        let replay = game.replay(&[(7, Color::Black), (3, Color::White)]);
+        let record = &pgn::parse_pgn(&replay).unwrap()[0];
+        assert_eq!(record.tag("White"), Some("3"));
+        assert_eq!(record.tag("Black"), Some("7"));
+        assert_eq!(record.tag("Termination"), Some("normal"));
+        assert_eq!(record.result, "0-1");
+        let sans: Vec<&str> =
+            record.moves.iter().map(|x| x.san.as_str()).collect();
+        assert_eq!(sans, ["f3", "e5", "g4", "Qh4#"]);
+        assert_eq!(record.replay().unwrap().export_to_fen(), game.state());
+    }
+
+    #[test]
+    fn test_claim_draw() {
+        let mut game = ChessGame::with_clock(None);
+        assert_eq!(
+            game.claim_draw(Color::White),
+            Err(DoActionError::NoDrawClaim)
+        );
+        for _ in 0..2 {
+            for (side, action) in [
+                (Color::White, "b1c3"),
+                (Color::Black, "b8c6"),
+                (Color::White, "c3b1"),
+                (Color::Black, "c6b8"),
+            ]
+            .iter()
+            {
+                assert_eq!(act(&mut game, *side, action), Ok(None));
+            }
+        }
+        assert_eq!(
+            game.claim_draw(Color::White),
+            Ok(Outcome::draw(Termination::ThreefoldRepetition))
+        );
+    }
+
+    #[test]
+    fn test_wish() {
+        let wish = |x: &str| x.parse::<ChessWish>().unwrap();
+        assert!(wish("white").is_match(&wish("black")));
+        assert!(!wish("white").is_match(&wish("white")));
+        assert!("white computer:0".parse::<ChessWish>().is_err());
+        assert!("red".parse::<ChessWish>().is_err());
+        assert!("white  computer".parse::<ChessWish>().is_err());
+
+        let computer =
+            ChessWish::bot(Color::Black, Bot::Computer(Level::DEFAULT));
+        assert!(wish("white computer").is_match(&computer));
+        assert!(computer.is_match(&wish("white computer")));
+        assert!(!wish("white computer:5").is_match(&computer));
+        assert!(!wish("white").is_match(&computer));
+        assert!(!wish("white computer").is_match(&wish("black computer")));
+        let other = ChessWish::bot(Color::White, Bot::Computer(Level::DEFAULT));
+        assert!(!other.is_match(&computer));
+
+        let stockfish = ChessWish::bot(Color::White, Bot::Uci("sf".into()));
+        assert!(wish("black uci:sf").is_match(&stockfish));
+        assert!(!wish("black uci:other").is_match(&stockfish));
+        assert!(!wish("black computer").is_match(&stockfish));
+        assert!("black uci:".parse::<ChessWish>().is_err());
+
+        let timed = wish("white computer:2 3+2");
+        assert_eq!(timed.opponent, Some(Bot::Computer(Level::new(2).unwrap())));
+        assert_eq!(timed.time_control, "3+2".parse().ok());
+        assert_eq!(wish("black 5+3d").time_control, "5+3d".parse().ok());
+        assert!("white 3+2 3+2".parse::<ChessWish>().is_err());
+        assert!("white 3+2 computer computer".parse::<ChessWish>().is_err());
+
+        assert!(wish("white 3+2").is_match(&wish("black 3+2")));
+        assert!(!wish("white 1+0").is_match(&wish("black 3+2")));
+        assert!(!wish("white 15+10").is_match(&wish("black")));
+        assert!(!wish("white 5+3").is_match(&wish("black 5+3d")));
+        assert!(wish("white computer 1+0").is_match(&computer));
+        assert_eq!(
+            ChessGame::new([&computer, &wish("white computer 1+0")])
+                .record
+                .tag("TimeControl"),
+            Some("60")
+        );
+
+        let any = wish("any 3+2");
+        assert!(any.is_match(&wish("white 3+2")));
+        assert!(any.is_match(&wish("any 3+2")));
+        assert!(!any.is_match(&wish("any 1+0")));
+        assert!(any.is_match(&wish("random 3+2")));
+    }
+
+    #[test]
+    fn test_buckets() {
+        let computer =
+            ChessWish::bot(Color::Black, Bot::Computer(Level::DEFAULT));
+        let mut wishes = vec![computer];
+        for x in &[
+            "white",
+            "black",
+            "any",
+            "white 3+2",
+            "any 3+2",
+            "black 1+0",
+            "white computer",
+            "any computer 3+2",
+            "white computer:5",
+        ] {
+            wishes.push(x.parse().unwrap());
+        }
+        for a in wishes.iter() {
+            for b in wishes.iter() {
+                let found = a.matching_buckets().contains(&b.bucket());
+                assert_eq!(a.is_match(b), found, "{:?} {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sides() {
+        use Color::{Black, White};
+        let wish = |x: &str| x.parse::<ChessWish>().unwrap();
+        let any = wish("any");
+        let none: &[Color] = &[];
+        assert_eq!(any.sides(&wish("white"), [none, none]), [Black, White]);
+        assert_eq!(
+            wish("black").sides(&any, [&[Black, Black], none]),
+            [Black, White]
+        );
+        assert_eq!(
+            wish("white").sides(&wish("black"), [&[White], &[Black]]),
+            [White, Black]
+        );
+        assert_eq!(
+            any.sides(&wish("random"), [&[White, White], &[White]]),
+            [Black, White]
+        );
+        assert_eq!(
+            any.sides(&any, [&[Black], &[White, Black]]),
+            [White, Black]
+        );
+        assert_eq!(any.sides(&any, [&[White], &[Black]]), [Black, White]);
+        let drawn: Vec<_> = (0..64)
+            .map(|_| any.sides(&any, [&[White, Black], none])[0])
+            .collect();
+        assert!(drawn.contains(&White) && drawn.contains(&Black));
+    }
+
+    #[test]
+    fn test_rating() {
+        let win = Outcome::win(Color::White, Termination::Checkmate);
+        assert_eq!(ChessGame::score(&win, Color::White), Some(1.0));
+        assert_eq!(ChessGame::score(&win, Color::Black), Some(0.0));
+        let draw = Outcome::draw(Termination::Stalemate);
+        assert_eq!(ChessGame::score(&draw, Color::Black), Some(0.5));
+        let aborted = Outcome::draw(Termination::Aborted);
+        assert_eq!(ChessGame::score(&aborted, Color::White), None);
+        let game = ChessGame::with_clock("3+2".parse().ok());
+        assert_eq!(game.category(), Category::Blitz);
+    }
+
+    #[test]
+    fn test_clock() {
+        let control = "1+0".parse().ok();
+        let mut game = ChessGame::with_clock(control);
+        let mut lone_king = ChessGame::with_clock(control);
+        lone_king
+            .board
+            .parse_fen("K7/8/8/8/8/8/7Q/k7 w - - 0 1")
+            .unwrap();
+        assert_eq!(act(&mut game, Color::White, "e2e4"), Ok(None));
+        assert!(game.record.moves[0].clock.is_some());
+        assert!(game.check_time().is_none());
+
+        // black has only a minute, so the flag falls by then
+        let later = Instant::now() + Duration::from_secs(61);
+        assert_eq!(
+            game.apply_at(Color::Black, "e7e5".parse().unwrap(), later),
+            Err(DoActionError::OutOfTime)
+        );
+        assert_eq!(
+            game.check_time_at(later),
+            Some(Outcome::win(Color::White, Termination::Timeout))
+        );
+        assert_eq!(
+            lone_king.check_time_at(later),
+            Some(Outcome::draw(Termination::Timeout))
+        );
     }
 }
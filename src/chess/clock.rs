@@ -0,0 +1,363 @@
+//! Chess clocks counting time players have left for the game
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use super::Color;
+
+/// How players get extra time for every move
+/// * `Fischer` - increment is added after the move
+/// * `Bronstein` - time spent on the move is given back, up to the
+///   increment
+/// * `SimpleDelay` - clock starts running only when the increment passes
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ClockMode {
+    Fischer,
+    Bronstein,
+    SimpleDelay,
+}
+
+/// Base time of each player and extra time for every move, written as
+/// minutes and seconds, e.g. `3+2`, with `b` suffix for Bronstein delay
+/// and `d` for simple delay, e.g. `5+3d`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+    pub mode: ClockMode,
+}
+
+/// Longest base time of a game
+pub const MAX_BASE: Duration = Duration::from_secs(24 * 60 * 60);
+/// Longest extra time for a move
+pub const MAX_INCREMENT: Duration = Duration::from_secs(60 * 60);
+
+/// Time control is malformed, negative or over `MAX_BASE`/`MAX_INCREMENT`
+#[derive(Debug)]
+pub enum ParseTimeControlError {
+    InvalidTimeControl,
+}
+
+impl FromStr for TimeControl {
+    type Err = ParseTimeControlError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (s, mode) = match s.as_bytes().last() {
+            Some(b'b') => (&s[..s.len() - 1], ClockMode::Bronstein),
+            Some(b'd') => (&s[..s.len() - 1], ClockMode::SimpleDelay),
+            _ => (s, ClockMode::Fischer),
+        };
+        let mut parts = s.splitn(2, '+');
+        let base = parts
+            .next()
+            .and_then(|x| x.parse::<f64>().ok())
+            .and_then(|x| Duration::try_from_secs_f64(x * 60.0).ok())
+            .filter(|x| *x <= MAX_BASE);
+        let increment = parts
+            .next()
+            .and_then(|x| x.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .filter(|x| *x <= MAX_INCREMENT);
+        match (base, increment) {
+            (Some(base), Some(increment))
+                if base.as_nanos() != 0 || increment.as_nanos() != 0 =>
+            {
+                Ok(TimeControl {
+                    base,
+                    increment,
+                    mode,
+                })
+            }
+            _ => Err(ParseTimeControlError::InvalidTimeControl),
+        }
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let minutes = self.base.as_secs_f64() / 60.0;
+        write!(f, "{}+{}", minutes, self.increment.as_secs())?;
+        match self.mode {
+            ClockMode::Fischer => Ok(()),
+            ClockMode::Bronstein => write!(f, "b"),
+            ClockMode::SimpleDelay => write!(f, "d"),
+        }
+    }
+}
+
+/// Kind of game by its expected duration, players are rated separately
+/// in every category
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+    Untimed,
+}
+
+impl Category {
+    /// Category of game with given time control
+    pub fn of(control: Option<TimeControl>) -> Category {
+        let control = match control {
+            Some(control) => control,
+            None => return Category::Untimed,
+        };
+        // game of 40 moves for each player, too long one is classical
+        let duration = control
+            .increment
+            .checked_mul(40)
+            .and_then(|x| x.checked_add(control.base))
+            .unwrap_or(Duration::MAX);
+        match duration.as_secs() {
+            x if x < 3 * 60 => Category::Bullet,
+            x if x < 8 * 60 => Category::Blitz,
+            x if x < 25 * 60 => Category::Rapid,
+            _ => Category::Classical,
+        }
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Category::Bullet => "bullet",
+            Category::Blitz => "blitz",
+            Category::Rapid => "rapid",
+            Category::Classical => "classical",
+            Category::Untimed => "untimed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl TimeControl {
+    /// Value of PGN `TimeControl` tag, it has no notation for delays
+    pub fn pgn_tag(&self) -> String {
+        match self.mode {
+            ClockMode::Fischer if self.increment.as_secs() == 0 => {
+                self.base.as_secs().to_string()
+            }
+            ClockMode::Fischer => {
+                format!("{}+{}", self.base.as_secs(), self.increment.as_secs())
+            }
+            _ => "?".to_string(),
+        }
+    }
+}
+
+/// Time players have left, clock of player to move is running
+#[derive(Debug, Clone)]
+pub struct Clock {
+    control: TimeControl,
+    white: Duration,
+    black: Duration,
+    turn: Color,
+    // when player to move started thinking
+    turn_started: Instant,
+}
+
+/// Clock as players see it, time is in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClockState {
+    pub white: u64,
+    pub black: u64,
+    /// Player whose clock is running
+    pub turn: Color,
+    /// Increment or delay of every move
+    pub increment: u64,
+}
+
+impl Clock {
+    /// Starts clock of white
+    pub fn new(control: TimeControl, now: Instant) -> Clock {
+        Clock {
+            control,
+            white: control.base,
+            black: control.base,
+            turn: Color::White,
+            turn_started: now,
+        }
+    }
+
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    /// Time player has left at the moment
+    pub fn left(&self, color: Color, now: Instant) -> Duration {
+        let left = match color {
+            Color::White => self.white,
+            Color::Black => self.black,
+        };
+        if color != self.turn {
+            return left;
+        }
+        let spent = now.saturating_duration_since(self.turn_started);
+        match self.control.mode {
+            ClockMode::SimpleDelay => left
+                .saturating_sub(spent.saturating_sub(self.control.increment)),
+            _ => left.saturating_sub(spent),
+        }
+    }
+
+    /// Moment player to move runs out of time, `None` if it's too far
+    /// to be represented
+    pub fn deadline(&self) -> Option<Instant> {
+        let left = self.left(self.turn, self.turn_started);
+        let left = match self.control.mode {
+            ClockMode::SimpleDelay => {
+                left.checked_add(self.control.increment)?
+            }
+            _ => left,
+        };
+        self.turn_started.checked_add(left)
+    }
+
+    pub fn is_flagged(&self, now: Instant) -> bool {
+        self.deadline().is_some_and(|x| now >= x)
+    }
+
+    /// Ends the turn of player to move and starts clock of the opponent
+    /// # Returns
+    /// * `Duration` - time player who moved has left, with extra time
+    pub fn press(&mut self, now: Instant) -> Duration {
+        let spent = now.saturating_duration_since(self.turn_started);
+        let extra = match self.control.mode {
+            ClockMode::Fischer => self.control.increment,
+            ClockMode::Bronstein => spent.min(self.control.increment),
+            ClockMode::SimpleDelay => Duration::from_secs(0),
+        };
+        let left = self.left(self.turn, now) + extra;
+        match self.turn {
+            Color::White => self.white = left,
+            Color::Black => self.black = left,
+        }
+        self.turn = !self.turn;
+        self.turn_started = now;
+        left
+    }
+
+    pub fn state(&self, now: Instant) -> ClockState {
+        ClockState {
+            white: self.left(Color::White, now).as_millis() as u64,
+            black: self.left(Color::Black, now).as_millis() as u64,
+            turn: self.turn,
+            increment: self.control.increment.as_millis() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(x: u64) -> Duration {
+        Duration::from_secs(x)
+    }
+
+    #[test]
+    fn test_parse_time_control() {
+        let control: TimeControl = "3+2".parse().unwrap();
+        assert_eq!(control.base, secs(180));
+        assert_eq!(control.increment, secs(2));
+        assert_eq!(control.mode, ClockMode::Fischer);
+        assert_eq!(control.pgn_tag(), "180+2");
+        let control: TimeControl = "0.5+0".parse().unwrap();
+        assert_eq!(control.base, secs(30));
+        assert_eq!(control.to_string(), "0.5+0");
+        assert_eq!(control.pgn_tag(), "30");
+        let control: TimeControl = "5+3d".parse().unwrap();
+        assert_eq!(control.mode, ClockMode::SimpleDelay);
+        assert_eq!(control.to_string(), "5+3d");
+        assert_eq!(control.pgn_tag(), "?");
+        assert_eq!(
+            "15+10b".parse::<TimeControl>().unwrap().to_string(),
+            "15+10b"
+        );
+        for x in &["0+0", "5", "+3", "5+", "5+x", "-1+3", "5+3x", "NaN+1"] {
+            assert!(x.parse::<TimeControl>().is_err(), "{}", x);
+        }
+        // too long times are rejected rather than overflowing
+        let invalid = [
+            "1e18+0",
+            "inf+0",
+            "-inf+0",
+            "1441+0",
+            "1+3601",
+            "1+18446744073709551615",
+            "1000000000000000+0",
+        ];
+        for x in invalid.iter() {
+            assert!(x.parse::<TimeControl>().is_err(), "{}", x);
+        }
+        let control: TimeControl = "1440+3600".parse().unwrap();
+        assert_eq!(
+            (control.base, control.increment),
+            (MAX_BASE, MAX_INCREMENT)
+        );
+    }
+
+    #[test]
+    fn test_category() {
+        let category = |x: &str| Category::of(x.parse().ok());
+        assert_eq!(category("1+0"), Category::Bullet);
+        assert_eq!(category("2+1"), Category::Bullet);
+        assert_eq!(category("3+2"), Category::Blitz);
+        assert_eq!(category("10+0"), Category::Rapid);
+        assert_eq!(category("15+10"), Category::Rapid);
+        assert_eq!(category("30+0"), Category::Classical);
+        assert_eq!(Category::of(None), Category::Untimed);
+        let longest = TimeControl {
+            base: Duration::MAX,
+            increment: Duration::MAX,
+            mode: ClockMode::Fischer,
+        };
+        assert_eq!(Category::of(Some(longest)), Category::Classical);
+        assert_eq!(Category::Blitz.to_string(), "blitz");
+    }
+
+    #[test]
+    fn test_clock() {
+        let start = Instant::now();
+        let at = |x| start + secs(x);
+        let clock = |x: &str| Clock::new(x.parse().unwrap(), start);
+
+        let mut fischer = clock("1+5");
+        assert_eq!(fischer.press(at(10)), secs(55));
+        assert_eq!(fischer.left(Color::Black, at(15)), secs(55));
+        assert_eq!(fischer.press(at(15)), secs(60));
+        assert_eq!(fischer.deadline(), Some(at(70)));
+        assert!(!fischer.is_flagged(at(69)));
+        assert!(fischer.is_flagged(at(70)));
+
+        let mut bronstein = clock("1+5b");
+        assert_eq!(bronstein.press(at(3)), secs(60));
+        assert_eq!(bronstein.press(at(13)), secs(55));
+
+        let mut delay = clock("1+5d");
+        assert_eq!(delay.left(Color::White, at(4)), secs(60));
+        assert_eq!(delay.press(at(4)), secs(60));
+        assert_eq!(delay.left(Color::Black, at(14)), secs(55));
+        assert_eq!(delay.deadline(), Some(at(69)));
+
+        let state = delay.state(at(14));
+        assert_eq!((state.white, state.black), (60_000, 55_000));
+        assert_eq!(state.turn, Color::Black);
+
+        // deadline that can't be represented never comes
+        let endless = Clock::new(
+            TimeControl {
+                base: Duration::MAX,
+                increment: secs(0),
+                mode: ClockMode::Fischer,
+            },
+            start,
+        );
+        assert_eq!(endless.deadline(), None);
+        assert!(!endless.is_flagged(at(1_000_000)));
+    }
+}
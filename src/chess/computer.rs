@@ -0,0 +1,202 @@
+//! Built-in computer opponent playing with the engine search
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use std::time::Duration;
+
+use actix::Addr;
+
+use super::clock::ClockState;
+use super::engine::{Limits, Search};
+use super::{Bot, ChessAction, ChessGame, ChessWish, Color};
+use crate::bot::{BotPlayer, Engine};
+use crate::domain::Game;
+use crate::runtime::DefaultGameServer;
+
+// size of transposition table of every computer player
+const TABLE_MEGABYTES: usize = 16;
+
+// moves computer expects to make with time it has left
+const MOVES_TO_GO: u64 = 30;
+
+/// Time computer may think over a move unless configured otherwise
+pub const DEFAULT_BUDGET: Duration = Duration::from_secs(2);
+
+/// Strength of computer player, from 1 to `Level::MAX`, higher levels
+/// search deeper
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Level(u8);
+
+#[derive(Debug)]
+pub enum ParseLevelError {
+    InvalidLevel,
+}
+
+impl Level {
+    pub const MAX: u8 = 8;
+
+    /// Level used when player doesn't choose one
+    pub const DEFAULT: Level = Level(3);
+
+    pub fn new(level: u8) -> Option<Level> {
+        if (1..=Level::MAX).contains(&level) {
+            Some(Level(level))
+        } else {
+            None
+        }
+    }
+
+    pub fn all() -> impl Iterator<Item = Level> {
+        (1..=Level::MAX).map(Level)
+    }
+
+    fn depth(self) -> u32 {
+        self.0 as u32
+    }
+}
+
+impl FromStr for Level {
+    type Err = ParseLevelError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse()
+            .ok()
+            .and_then(Level::new)
+            .ok_or(ParseLevelError::InvalidLevel)
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Engine playing single game with search limited by level and time
+pub struct ChessEngine {
+    game: ChessGame,
+    search: Search,
+    limits: Limits,
+    budget: Duration,
+}
+
+impl ChessEngine {
+    /// # Arguments
+    /// * `level` - strength of the engine
+    /// * `budget` - time engine may think over single move
+    pub fn new(level: Level, budget: Duration) -> ChessEngine {
+        ChessEngine {
+            game: ChessGame::with_clock(None),
+            search: Search::new(TABLE_MEGABYTES),
+            limits: Limits {
+                depth: Some(level.depth()),
+                time: Some(budget),
+            },
+            budget,
+        }
+    }
+}
+
+impl Engine<ChessGame> for ChessEngine {
+    fn apply(&mut self, action: &ChessAction) {
+        let side = self.game.board.turn();
+        if let Err(err) = self.game.apply(side, *action) {
+            log::error!("Engine can't follow {}: {}", action, err);
+        }
+    }
+
+    fn to_move(&self) -> Color {
+        self.game.board.turn()
+    }
+
+    fn think(&mut self) -> Option<ChessAction> {
+        let best = self.search.run(&self.game.board, self.limits, |info| {
+            log::debug!(
+                "depth {} score {:?} nodes {}",
+                info.depth,
+                info.score,
+                info.nodes
+            )
+        });
+        best.map(ChessAction::from_move)
+    }
+
+    /// Spends even share of time left on the move, within the budget
+    fn clock(&mut self, side: Color, clock: &ClockState) {
+        let left = match side {
+            Color::White => clock.white,
+            Color::Black => clock.black,
+        };
+        let share = Duration::from_millis(left / MOVES_TO_GO);
+        self.limits.time = Some(share.min(self.budget));
+    }
+}
+
+/// Reads time budget of a move in milliseconds, `None` if it's invalid
+/// or zero
+pub fn parse_budget(s: &str) -> Option<Duration> {
+    match s.parse::<u64>() {
+        Ok(0) | Err(_) => None,
+        Ok(millis) => Some(Duration::from_millis(millis)),
+    }
+}
+
+/// Starts computer players of every level waiting for both colors, each
+/// of them spending up to `budget` on a move
+pub fn start_computers(
+    server: &Addr<DefaultGameServer<ChessGame>>,
+    budget: Duration,
+) {
+    for level in Level::all() {
+        for color in [Color::White, Color::Black].iter() {
+            BotPlayer::spawn(
+                server.clone(),
+                ChessWish::bot(*color, Bot::Computer(level)),
+                move || ChessEngine::new(level, budget),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_plays_legal_moves() {
+        let mut white = ChessEngine::new(Level::new(2).unwrap(), budget());
+        let mut black = ChessEngine::new(Level::new(1).unwrap(), budget());
+        for _ in 0..10 {
+            let action = white.think().unwrap();
+            white.apply(&action);
+            black.apply(&action);
+            assert_eq!(black.to_move(), Color::Black);
+            let action = black.think().unwrap();
+            black.apply(&action);
+            white.apply(&action);
+        }
+        assert_eq!(white.game.state(), black.game.state());
+        assert_eq!(white.game.board.plies(), 20);
+    }
+
+    #[test]
+    fn test_budget() {
+        let budget = parse_budget("500").unwrap();
+        let mut engine = ChessEngine::new(Level::DEFAULT, budget);
+        assert_eq!(engine.limits.time, Some(Duration::from_millis(500)));
+        let clock = ClockState {
+            white: 600_000,
+            black: 600_000,
+            turn: Color::White,
+            increment: 0,
+        };
+        engine.clock(Color::White, &clock);
+        assert_eq!(engine.limits.time, Some(Duration::from_millis(500)));
+        for x in &["0", "-1", "x", ""] {
+            assert_eq!(parse_budget(x), None, "{}", x);
+        }
+    }
+
+    fn budget() -> Duration {
+        Duration::from_secs(1)
+    }
+}
@@ -0,0 +1,1471 @@
+use std::fmt;
+use std::ops::Deref;
+
+use serde::Serialize;
+
+use bitboard::{
+    bishop_attacks, king_attacks, knight_attacks, pawn_attacks, queen_attacks,
+    rook_attacks, Bitboard,
+};
+use Color::{Black, White};
+
+mod bitboard;
+mod fen;
+mod san;
+mod search;
+mod zobrist;
+
+pub use fen::ParseFenError;
+pub use san::{ParseSanError, San};
+pub use search::{Limits, Score, Search, SearchInfo, MAX_DEPTH};
+
+const START_POS: [[char; 8]; 8] = [
+    ['r', 'p', '.', '.', '.', '.', 'P', 'R'],
+    ['n', 'p', '.', '.', '.', '.', 'P', 'N'],
+    ['b', 'p', '.', '.', '.', '.', 'P', 'B'],
+    ['q', 'p', '.', '.', '.', '.', 'P', 'Q'],
+    ['k', 'p', '.', '.', '.', '.', 'P', 'K'],
+    ['b', 'p', '.', '.', '.', '.', 'P', 'B'],
+    ['n', 'p', '.', '.', '.', '.', 'P', 'N'],
+    ['r', 'p', '.', '.', '.', '.', 'P', 'R'],
+];
+
+// kinds of figures, index into `BoardState::figures`
+const PAWN: usize = 0;
+const KNIGHT: usize = 1;
+const BISHOP: usize = 2;
+const ROOK: usize = 3;
+const QUEEN: usize = 4;
+const KING: usize = 5;
+
+const FIGURES: [char; 6] = ['P', 'N', 'B', 'R', 'Q', 'K'];
+
+const LIGHT_SQUARES: Bitboard = Bitboard(0x55aa_55aa_55aa_55aa);
+
+// no position has more legal moves
+const MAX_MOVES: usize = 256;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    Black,
+    White,
+}
+
+impl Color {
+    /// Index into `BoardState::players`
+    fn index(self) -> usize {
+        match self {
+            White => 0,
+            Black => 1,
+        }
+    }
+}
+
+impl std::ops::Not for Color {
+    type Output = Color;
+
+    fn not(self) -> Self::Output {
+        match self {
+            White => Black,
+            Black => White,
+        }
+    }
+}
+
+//TODO replace with something smarter
+// TODO rewrite to bitfield
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct CastlingRights {
+    wk: bool,
+    wq: bool,
+    bk: bool,
+    bq: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Castling {
+    WK,
+    WQ,
+    BK,
+    BQ,
+}
+
+impl Castling {
+    /// Squares king moves from and to, UCI writes castling as this move
+    pub fn king_squares(self) -> (Square, Square) {
+        match self {
+            Castling::WK => (Square(4, 7), Square(6, 7)),
+            Castling::WQ => (Square(4, 7), Square(2, 7)),
+            Castling::BK => (Square(4, 0), Square(6, 0)),
+            Castling::BQ => (Square(4, 0), Square(2, 0)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Square(pub u8, pub u8);
+
+/// Figure pawn can be promoted to
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Promotion {
+    Queen,
+    Rook,
+    Bishop,
+    Knight,
+}
+
+const PROMOTIONS: [Promotion; 4] = [
+    Promotion::Queen,
+    Promotion::Rook,
+    Promotion::Bishop,
+    Promotion::Knight,
+];
+
+impl Promotion {
+    fn kind(self) -> usize {
+        match self {
+            Promotion::Queen => QUEEN,
+            Promotion::Rook => ROOK,
+            Promotion::Bishop => BISHOP,
+            Promotion::Knight => KNIGHT,
+        }
+    }
+
+    fn figure(self, color: Color) -> char {
+        let figure = match self {
+            Promotion::Queen => 'q',
+            Promotion::Rook => 'r',
+            Promotion::Bishop => 'b',
+            Promotion::Knight => 'n',
+        };
+        match color {
+            White => figure.to_ascii_uppercase(),
+            Black => figure,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Move {
+    Move(Square, Square),
+    Promotion(Square, Square, Promotion),
+    Castling(Castling),
+    Surrender(Color),
+}
+
+/// Result of finished game: winner, if there is one, and why game ended
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Outcome {
+    pub winner: Option<Color>,
+    pub termination: Termination,
+}
+
+/// Reason why the game ended
+/// * `Checkmate` - player to move is in check and has no legal moves
+/// * `Resignation` - player gave up
+/// * `Timeout` - player ran out of time, it is a draw if the opponent
+///   can't checkmate
+/// * `Abandonment` - player left the game
+/// * `Aborted` - game was left before both players made a move
+/// * `Agreement` - players agreed to a draw
+/// * `Stalemate` - player to move has no legal moves and is not in check
+/// * `FiftyMoves` - fifty moves by each side without capture or pawn move,
+///   has to be claimed
+/// * `SeventyFiveMoves` - seventy five moves by each side without capture
+///   or pawn move
+/// * `ThreefoldRepetition` - same position occurred three times, has to be
+///   claimed
+/// * `FivefoldRepetition` - same position occurred five times
+/// * `InsufficientMaterial` - neither side can checkmate by any sequence of
+///   legal moves
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Termination {
+    Checkmate,
+    Resignation,
+    Timeout,
+    Abandonment,
+    Aborted,
+    Agreement,
+    Stalemate,
+    FiftyMoves,
+    SeventyFiveMoves,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    InsufficientMaterial,
+}
+
+/// Reason why the move can't be made
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidMove {
+    NotYourTurn,
+    /// Moving the figure would expose king to attack
+    PiecePinned,
+    /// King is in check or moves under attack
+    LeavesKingInCheck,
+    /// Figure can't move this way, or player has no figure on the square
+    IllegalPattern,
+}
+
+impl Outcome {
+    pub fn win(winner: Color, termination: Termination) -> Outcome {
+        Outcome {
+            winner: Some(winner),
+            termination,
+        }
+    }
+
+    pub fn draw(termination: Termination) -> Outcome {
+        Outcome {
+            winner: None,
+            termination,
+        }
+    }
+}
+
+/// Legal moves of the position, kept inline so that generating them
+/// doesn't allocate
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    fn new() -> MoveList {
+        MoveList {
+            moves: [Move::Surrender(White); MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, player_move: Move) {
+        self.moves[self.len] = player_move;
+        self.len += 1;
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+/// Record of the move made by `make_move` with everything the move
+/// destroys, so that `unmake_move` can restore previous position exactly
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Undo {
+    player_move: Move,
+    // kind of captured figure, color is opposite to the one of `turn`
+    captured: Option<usize>,
+    turn: Color,
+    castling_rights: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    fullmoves: u32,
+    hash: u64,
+}
+
+#[derive(Clone)]
+pub struct BoardState {
+    // squares of figures of every kind, both players, see `FIGURES`
+    figures: [Bitboard; 6],
+    // squares of figures of every player, white first
+    players: [Bitboard; 2],
+    turn: Color,
+    castling_rights: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    fullmoves: u32,
+    // Zobrist hash of the position, see `hash`
+    hash: u64,
+    // hashes of all positions since the game start, including current one
+    history: Vec<u64>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct RelMov(i8, i8);
+
+#[derive(Debug)]
+pub enum ConvertStrToU8Error {
+    LetterOutOfRange,
+    ArgumentTooShort,
+    ArgumentIsNotConvertibleToNumber,
+}
+
+//Converts string representation like "e4" to coords; Coords (0, 0) is a8
+pub fn convert_str_to_u8(square: &str) -> Result<Square, ConvertStrToU8Error> {
+    let mut arg = square.chars();
+
+    let letter = match arg.next() {
+        Some(x) => x,
+        None => return Err(ConvertStrToU8Error::ArgumentTooShort),
+    };
+
+    let number: u8 = match arg.next() {
+        Some(x) => match x.to_digit(10) {
+            Some(y @ 1..=8) => y as u8,
+            _ => {
+                return Err(
+                    ConvertStrToU8Error::ArgumentIsNotConvertibleToNumber,
+                )
+            }
+        },
+        None => return Err(ConvertStrToU8Error::ArgumentTooShort),
+    };
+
+    let vertical = match letter {
+        'a' | 'A' => 0,
+        'b' | 'B' => 1,
+        'c' | 'C' => 2,
+        'd' | 'D' => 3,
+        'e' | 'E' => 4,
+        'f' | 'F' => 5,
+        'g' | 'G' => 6,
+        'h' | 'H' => 7,
+        _ => return Err(ConvertStrToU8Error::LetterOutOfRange),
+    };
+    Ok(Square(vertical, 8 - number))
+}
+
+#[derive(Debug)]
+pub enum ConvertSquareToStrError {
+    IncorrectHorizontalInput,
+    IncorrectVerticalInput,
+}
+
+pub fn convert_to_text_notation(
+    square: Square,
+) -> Result<String, ConvertSquareToStrError> {
+    let square_x = square.0;
+    let square_y = square.1;
+
+    let letter = match square_x {
+        0 => 'a',
+        1 => 'b',
+        2 => 'c',
+        3 => 'd',
+        4 => 'e',
+        5 => 'f',
+        6 => 'g',
+        7 => 'h',
+        _ => return Err(ConvertSquareToStrError::IncorrectHorizontalInput),
+    };
+    if square_y > 7 {
+        return Err(ConvertSquareToStrError::IncorrectVerticalInput);
+    }
+    Ok(format!("{}{}", letter, 8 - square_y))
+}
+
+/// Reason UCI move can't be read
+#[derive(Debug, PartialEq)]
+pub enum ParseUciError {
+    /// Move doesn't start with two squares
+    InvalidSquare,
+    /// Promotion suffix is not one of `q`, `r`, `b`, `n`
+    InvalidPromotion,
+}
+
+/// Reads move in UCI long algebraic notation, e.g. `e2e4` or `e7e8q`,
+/// without the board it is not known if king move is castling
+pub fn convert_uci_to_squares(
+    uci: &str,
+) -> Result<(Square, Square, Option<Promotion>), ParseUciError> {
+    if !uci.is_ascii() || uci.len() < 4 || uci.len() > 5 {
+        return Err(ParseUciError::InvalidSquare);
+    }
+    let from = convert_str_to_u8(&uci[..2])
+        .map_err(|_| ParseUciError::InvalidSquare)?;
+    let to = convert_str_to_u8(&uci[2..4])
+        .map_err(|_| ParseUciError::InvalidSquare)?;
+    let promotion = match &uci[4..] {
+        "" => None,
+        x => Some(
+            PROMOTIONS
+                .iter()
+                .find(|p| p.figure(Black).to_string() == x)
+                .copied()
+                .ok_or(ParseUciError::InvalidPromotion)?,
+        ),
+    };
+    Ok((from, to, promotion))
+}
+
+/// Row of castling, files rook moves from and to and file king moves to
+fn castling_squares(castling: Castling) -> (u8, u8, u8, u8) {
+    let row = match castling {
+        Castling::WK | Castling::WQ => 7,
+        Castling::BK | Castling::BQ => 0,
+    };
+    match castling {
+        Castling::WK | Castling::BK => (row, 7, 5, 6),
+        Castling::WQ | Castling::BQ => (row, 0, 3, 2),
+    }
+}
+
+//Basically adding coords with range check
+fn get_relative_coords(square: Square, offset: RelMov) -> Option<Square> {
+    let square_x: i8 = square.0 as i8;
+    let square_y: i8 = square.1 as i8;
+
+    if let (0..=7, 0..=7) = (square_x + offset.0, square_y + offset.1) {
+        Some(Square(
+            (square_x + offset.0) as u8,
+            (square_y + offset.1) as u8,
+        ))
+    } else {
+        None
+    }
+}
+
+impl Default for BoardState {
+    fn default() -> Self {
+        BoardState::new()
+    }
+}
+
+impl BoardState {
+    pub fn new() -> BoardState {
+        let mut board = BoardState {
+            figures: [Bitboard::EMPTY; 6],
+            players: [Bitboard::EMPTY; 2],
+            turn: White,
+            castling_rights: CastlingRights {
+                wk: true,
+                wq: true,
+                bk: true,
+                bq: true,
+            },
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmoves: 1,
+            hash: 0,
+            history: Vec::new(),
+        };
+        board.set_placement(&START_POS);
+        board.hash = board.full_hash();
+        board.history.push(board.hash);
+        board
+    }
+
+    pub fn turn(&self) -> Color {
+        self.turn
+    }
+
+    pub fn fullmoves(&self) -> u32 {
+        self.fullmoves
+    }
+
+    /// Zobrist hash of the position: placement of figures, player to move,
+    /// castling rights and en passant file if pawn stands ready to capture,
+    /// clocks are not included. Positions reached by different move
+    /// orders have same hash
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Kind and color of figure on the square, if there is one
+    fn figure_at(&self, square: Square) -> Option<(usize, Color)> {
+        let color = if self.players[0].contains(square) {
+            White
+        } else if self.players[1].contains(square) {
+            Black
+        } else {
+            return None;
+        };
+        let kind = self.figures.iter().position(|x| x.contains(square))?;
+        Some((kind, color))
+    }
+
+    /// Figure on the square as written in FEN, `.` for empty square
+    fn get(&self, square: Square) -> char {
+        match self.figure_at(square) {
+            Some((kind, White)) => FIGURES[kind],
+            Some((kind, Black)) => FIGURES[kind].to_ascii_lowercase(),
+            None => '.',
+        }
+    }
+
+    fn place(&mut self, square: Square, kind: usize, color: Color) {
+        let bit = Bitboard::square(square);
+        self.figures[kind] |= bit;
+        self.players[color.index()] |= bit;
+        self.hash ^= zobrist::figure(kind, color, square);
+    }
+
+    fn remove(&mut self, square: Square) {
+        if let Some((kind, color)) = self.figure_at(square) {
+            let bit = !Bitboard::square(square);
+            self.figures[kind] &= bit;
+            self.players[color.index()] &= bit;
+            self.hash ^= zobrist::figure(kind, color, square);
+        }
+    }
+
+    /// Puts figures written as in FEN, `board[x][y]` where (0, 0) is a8
+    fn set_placement(&mut self, board: &[[char; 8]; 8]) {
+        self.figures = [Bitboard::EMPTY; 6];
+        self.players = [Bitboard::EMPTY; 2];
+        for (x, column) in board.iter().enumerate() {
+            for (y, figure) in column.iter().enumerate() {
+                let kind = FIGURES
+                    .iter()
+                    .position(|x| *x == figure.to_ascii_uppercase());
+                let color = if figure.is_uppercase() { White } else { Black };
+                if let Some(kind) = kind {
+                    self.place(Square(x as u8, y as u8), kind, color);
+                }
+            }
+        }
+    }
+
+    fn occupied(&self) -> Bitboard {
+        self.players[0] | self.players[1]
+    }
+
+    /// Figures of given kind and color
+    fn pieces(&self, kind: usize, color: Color) -> Bitboard {
+        self.figures[kind] & self.players[color.index()]
+    }
+
+    /// Makes the move if it is legal
+    /// # Returns
+    /// * `Some(Outcome)` - move is legal and ends the game
+    /// * `None` - move is legal and game continues
+    /// * `InvalidMove` - move can't be made, see enum for reasons
+    pub fn validate_move(
+        &mut self,
+        player_move: Move,
+        player_color: Color,
+    ) -> Result<Option<Outcome>, InvalidMove> {
+        if let Move::Surrender(x) = player_move {
+            return Ok(Some(self.handle_surrender(x)));
+        }
+        if player_color != self.turn {
+            return Err(InvalidMove::NotYourTurn);
+        }
+        if !self.legal_moves().contains(&player_move) {
+            return Err(self.invalid_move_reason(player_move));
+        }
+        self.make_move(player_move);
+        Ok(self.game_result())
+    }
+
+    /// Makes the move without checking if it is legal, the move should
+    /// come from `legal_moves`
+    /// # Returns
+    /// * `Undo` - record to take the move back with `unmake_move`
+    pub fn make_move(&mut self, player_move: Move) -> Undo {
+        let undo = self.commit_move(player_move);
+        self.history.push(self.hash);
+        undo
+    }
+
+    /// Takes back the last move made by `make_move`
+    pub fn unmake_move(&mut self, undo: Undo) {
+        self.history.pop();
+        self.uncommit_move(undo);
+    }
+
+    /// Reads move in UCI long algebraic notation, see `uci_move`
+    pub fn parse_uci(&self, uci: &str) -> Result<Move, ParseUciError> {
+        let (from, to, promotion) = convert_uci_to_squares(uci)?;
+        Ok(self.uci_move(from, to, promotion))
+    }
+
+    /// Move of figure from one square to another as UCI sees it, king
+    /// moving two squares from its initial square is castling
+    pub fn uci_move(
+        &self,
+        from: Square,
+        to: Square,
+        promotion: Option<Promotion>,
+    ) -> Move {
+        let castling = match (self.get(from), from, to) {
+            ('K', Square(4, 7), Square(6, 7)) => Some(Castling::WK),
+            ('K', Square(4, 7), Square(2, 7)) => Some(Castling::WQ),
+            ('k', Square(4, 0), Square(6, 0)) => Some(Castling::BK),
+            ('k', Square(4, 0), Square(2, 0)) => Some(Castling::BQ),
+            _ => None,
+        };
+        match (castling, promotion) {
+            (Some(castling), _) => Move::Castling(castling),
+            (None, Some(promotion)) => Move::Promotion(from, to, promotion),
+            (None, None) => Move::Move(from, to),
+        }
+    }
+
+    /// Draw which may be claimed in current position
+    pub fn draw_claim(&self) -> Option<Termination> {
+        if self.repetitions() >= 3 {
+            Some(Termination::ThreefoldRepetition)
+        } else if self.halfmove_clock >= 100 {
+            Some(Termination::FiftyMoves)
+        } else {
+            None
+        }
+    }
+
+    /// Number of half-moves made since the start or the last parsed FEN
+    pub fn plies(&self) -> usize {
+        self.history.len().saturating_sub(1)
+    }
+
+    /// All moves player to move can make according to the rules
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.move_list().to_vec()
+    }
+
+    /// Same as `legal_moves`, but without allocation
+    pub fn move_list(&self) -> MoveList {
+        let player_color = self.turn;
+        let mut board = self.without_history();
+        let mut result = MoveList::new();
+        for kind in 0..FIGURES.len() {
+            for from in self.pieces(kind, player_color) {
+                for to in self.figure_targets(kind, player_color, from) {
+                    if kind == PAWN && (to.1 == 0 || to.1 == 7) {
+                        for promotion in PROMOTIONS.iter() {
+                            let x = Move::Promotion(from, to, *promotion);
+                            if board.check_if_safe_for_king(x, player_color) {
+                                result.push(x);
+                            }
+                        }
+                    } else if board.check_if_safe_for_king(
+                        Move::Move(from, to),
+                        player_color,
+                    ) {
+                        result.push(Move::Move(from, to));
+                    }
+                }
+            }
+        }
+        self.castling_possible_moves(player_color, &mut result);
+        result
+    }
+
+    /// Counts leaf nodes of the move tree of given depth, used to check
+    /// correctness of move generation
+    pub fn perft(&self, depth: u32) -> u64 {
+        self.without_history().count_leaves(depth)
+    }
+
+    /// Same as `perft`, but splits count by the first move
+    pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        let mut board = self.without_history();
+        self.move_list()
+            .iter()
+            .map(|x| {
+                let undo = board.commit_move(*x);
+                let count = board.count_leaves(depth.saturating_sub(1));
+                board.uncommit_move(undo);
+                (*x, count)
+            })
+            .collect()
+    }
+
+    fn count_leaves(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.move_list();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves
+            .iter()
+            .map(|x| {
+                let undo = self.commit_move(*x);
+                let count = self.count_leaves(depth - 1);
+                self.uncommit_move(undo);
+                count
+            })
+            .sum()
+    }
+
+    pub fn is_check(&self) -> bool {
+        match self.get_king(self.turn) {
+            Some(king) => self.is_attacked(king, !self.turn),
+            None => false,
+        }
+    }
+
+    /// Makes the move without checking if it is legal and without
+    /// updating position history
+    fn commit_move(&mut self, player_move: Move) -> Undo {
+        let mut undo = Undo {
+            player_move,
+            captured: None,
+            turn: self.turn,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmoves: self.fullmoves,
+            hash: self.hash,
+        };
+        let state_hash = self.state_hash();
+        let mut en_passant = None;
+        match player_move {
+            Move::Move(move_from, move_to)
+            | Move::Promotion(move_from, move_to, _) => {
+                let (mut kind, color) = match self.figure_at(move_from) {
+                    Some(x) => x,
+                    None => return undo,
+                };
+                self.halfmove_clock += 1;
+                if let Some((captured, _)) = self.figure_at(move_to) {
+                    self.halfmove_clock = 0;
+                    undo.captured = Some(captured);
+                    self.remove(move_to);
+                }
+                if kind == PAWN {
+                    self.halfmove_clock = 0;
+                    if Some(move_to) == self.en_passant {
+                        self.remove(Square(move_to.0, move_from.1));
+                        undo.captured = Some(PAWN);
+                    }
+                    if (move_from.1 as i8 - move_to.1 as i8).abs() == 2 {
+                        let row = (move_from.1 + move_to.1) / 2;
+                        en_passant = Some(Square(move_from.0, row));
+                    }
+                }
+                if let Move::Promotion(_, _, promotion) = player_move {
+                    kind = promotion.kind();
+                }
+                self.remove(move_from);
+                self.place(move_to, kind, color);
+                self.revoke_castling_rights(move_from);
+                self.revoke_castling_rights(move_to);
+            }
+            Move::Castling(castling) => {
+                self.halfmove_clock += 1;
+                self.commit_castling(castling);
+            }
+            Move::Surrender(_) => return undo,
+        }
+        if self.turn == Black {
+            self.fullmoves += 1;
+        }
+        self.en_passant = en_passant;
+        self.turn = !self.turn;
+        self.hash ^= state_hash ^ self.state_hash();
+        undo
+    }
+
+    /// Restores position before the move made by `commit_move`
+    fn uncommit_move(&mut self, undo: Undo) {
+        let color = undo.turn;
+        match undo.player_move {
+            Move::Move(move_from, move_to)
+            | Move::Promotion(move_from, move_to, _) => {
+                // figure is missing if commit_move did nothing
+                let mut kind = match self.figure_at(move_to) {
+                    Some((kind, x)) if x == color && self.turn != color => kind,
+                    _ => return,
+                };
+                if let Move::Promotion(..) = undo.player_move {
+                    kind = PAWN;
+                }
+                self.remove(move_to);
+                self.place(move_from, kind, color);
+                if let Some(captured) = undo.captured {
+                    let square =
+                        if kind == PAWN && Some(move_to) == undo.en_passant {
+                            Square(move_to.0, move_from.1)
+                        } else {
+                            move_to
+                        };
+                    self.place(square, captured, !color);
+                }
+            }
+            Move::Castling(castling) => {
+                let (row, rook_from, rook_to, king_to) =
+                    castling_squares(castling);
+                self.remove(Square(king_to, row));
+                self.remove(Square(rook_to, row));
+                self.place(Square(4, row), KING, color);
+                self.place(Square(rook_from, row), ROOK, color);
+            }
+            Move::Surrender(_) => (),
+        }
+        self.turn = undo.turn;
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmoves = undo.fullmoves;
+        self.hash = undo.hash;
+    }
+
+    /// Castling is not allowed after king or rook left its square,
+    /// or rook was captured
+    fn revoke_castling_rights(&mut self, square: Square) {
+        match (square.0, square.1) {
+            (4, 7) => {
+                self.castling_rights.wk = false;
+                self.castling_rights.wq = false;
+            }
+            (7, 7) => self.castling_rights.wk = false,
+            (0, 7) => self.castling_rights.wq = false,
+            (4, 0) => {
+                self.castling_rights.bk = false;
+                self.castling_rights.bq = false;
+            }
+            (7, 0) => self.castling_rights.bk = false,
+            (0, 0) => self.castling_rights.bq = false,
+            _ => (),
+        }
+    }
+
+    fn commit_castling(&mut self, castling: Castling) {
+        let (row, rook_from, rook_to, king_to) = castling_squares(castling);
+        self.remove(Square(4, row));
+        self.remove(Square(rook_from, row));
+        self.place(Square(king_to, row), KING, self.turn);
+        self.place(Square(rook_to, row), ROOK, self.turn);
+        self.revoke_castling_rights(Square(4, row));
+    }
+
+    /// Checks if square is attacked by any figure of given color
+    fn is_attacked(&self, square: Square, by_color: Color) -> bool {
+        let occupied = self.occupied();
+        let pieces = |kind| self.pieces(kind, by_color);
+        let attackers = (pawn_attacks(!by_color, square) & pieces(PAWN))
+            | (knight_attacks(square) & pieces(KNIGHT))
+            | (king_attacks(square) & pieces(KING))
+            | (bishop_attacks(square, occupied)
+                & (pieces(BISHOP) | pieces(QUEEN)))
+            | (rook_attacks(square, occupied) & (pieces(ROOK) | pieces(QUEEN)));
+        !attackers.is_empty()
+    }
+
+    fn castling_possible_moves(
+        &self,
+        player_color: Color,
+        moves: &mut MoveList,
+    ) {
+        let (row, king_side, queen_side) = match player_color {
+            White => (7, Castling::WK, Castling::WQ),
+            Black => (0, Castling::BK, Castling::BQ),
+        };
+        let (king_allowed, queen_allowed) = match player_color {
+            White => (self.castling_rights.wk, self.castling_rights.wq),
+            Black => (self.castling_rights.bk, self.castling_rights.bq),
+        };
+        if !(king_allowed || queen_allowed) || self.is_check() {
+            return;
+        }
+        let occupied = self.occupied();
+        let is_free = |empty: &[u8], safe: &[u8]| {
+            empty.iter().all(|x| !occupied.contains(Square(*x, row)))
+                && safe
+                    .iter()
+                    .all(|x| !self.is_attacked(Square(*x, row), !player_color))
+        };
+        if king_allowed && is_free(&[5, 6], &[5, 6]) {
+            moves.push(Move::Castling(king_side));
+        }
+        if queen_allowed && is_free(&[1, 2, 3], &[2, 3]) {
+            moves.push(Move::Castling(queen_side));
+        }
+    }
+
+    fn handle_surrender(&mut self, player_color: Color) -> Outcome {
+        Outcome::win(!player_color, Termination::Resignation)
+    }
+
+    /// Finds out why move, which is not among legal ones, can't be made
+    /// by player to move
+    fn invalid_move_reason(&self, player_move: Move) -> InvalidMove {
+        let (from, to) = match player_move {
+            Move::Move(from, to) | Move::Promotion(from, to, _) => (from, to),
+            _ => return InvalidMove::IllegalPattern,
+        };
+        let kind = match self.figure_at(from) {
+            Some((kind, color)) if color == self.turn => kind,
+            _ => return InvalidMove::IllegalPattern,
+        };
+        let is_promotion = matches!(player_move, Move::Promotion(..));
+        if is_promotion != (kind == PAWN && (to.1 == 0 || to.1 == 7))
+            || !self.figure_targets(kind, self.turn, from).contains(to)
+        {
+            InvalidMove::IllegalPattern
+        } else if self.is_check() || kind == KING {
+            InvalidMove::LeavesKingInCheck
+        } else {
+            InvalidMove::PiecePinned
+        }
+    }
+
+    fn get_king(&self, player_color: Color) -> Option<Square> {
+        self.pieces(KING, player_color).first()
+    }
+
+    /// Checks that after the move king of player is not attacked
+    fn check_if_safe_for_king(
+        &mut self,
+        player_move: Move,
+        player_color: Color,
+    ) -> bool {
+        let undo = self.commit_move(player_move);
+        let safe = match self.get_king(player_color) {
+            Some(king_pos) => !self.is_attacked(king_pos, !player_color),
+            None => true,
+        };
+        self.uncommit_move(undo);
+        safe
+    }
+
+    /// Copy of the board without position history, cheap to make
+    fn without_history(&self) -> BoardState {
+        BoardState {
+            history: Vec::new(),
+            ..*self
+        }
+    }
+
+    /// Part of the hash which doesn't depend on figures, en passant file
+    /// is included only if there is pawn to capture it
+    fn state_hash(&self) -> u64 {
+        let rights = [
+            self.castling_rights.wk,
+            self.castling_rights.wq,
+            self.castling_rights.bk,
+            self.castling_rights.bq,
+        ];
+        let mut hash = (0..rights.len())
+            .filter(|x| rights[*x])
+            .fold(0, |acc, x| acc ^ zobrist::castling(x));
+        if let Some(square) = self.en_passant {
+            let capturing =
+                pawn_attacks(!self.turn, square) & self.pieces(PAWN, self.turn);
+            if !capturing.is_empty() {
+                hash ^= zobrist::en_passant(square.0);
+            }
+        }
+        if self.turn == Black {
+            hash ^= zobrist::BLACK_TO_MOVE;
+        }
+        hash
+    }
+
+    /// Computes hash of the position from scratch
+    fn full_hash(&self) -> u64 {
+        let mut hash = self.state_hash();
+        for kind in 0..FIGURES.len() {
+            for &color in [White, Black].iter() {
+                for square in self.pieces(kind, color) {
+                    hash ^= zobrist::figure(kind, color, square);
+                }
+            }
+        }
+        hash
+    }
+
+    /// How many times current position has occurred, positions before
+    /// last capture or pawn move can't be repeated
+    fn repetitions(&self) -> usize {
+        let current = match self.history.last() {
+            Some(x) => x,
+            None => return 0,
+        };
+        self.history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize + 1)
+            .filter(|x| *x == current)
+            .count()
+    }
+
+    /// Checks that player has material to checkmate the opponent by some
+    /// sequence of legal moves
+    pub fn can_checkmate(&self, color: Color) -> bool {
+        let figures = self.players[color.index()] & !self.figures[KING];
+        !figures.is_empty() && !self.is_insufficient_material()
+    }
+
+    /// Checks that neither side has material to checkmate: lone kings,
+    /// single minor figure, or only bishops all on same colored squares
+    fn is_insufficient_material(&self) -> bool {
+        let majors =
+            self.figures[PAWN] | self.figures[ROOK] | self.figures[QUEEN];
+        let knights = self.figures[KNIGHT];
+        let bishops = self.figures[BISHOP];
+        if !majors.is_empty() {
+            return false;
+        }
+        (knights | bishops).count() <= 1
+            || (knights.is_empty()
+                && ((bishops & LIGHT_SQUARES).is_empty()
+                    || (bishops & !LIGHT_SQUARES).is_empty()))
+    }
+
+    /// Result of the game after the last move: checkmate, draw which is
+    /// not claimed but happens automatically, or game continues
+    fn game_result(&self) -> Option<Outcome> {
+        if self.move_list().is_empty() {
+            if !self.is_check() {
+                return Some(Outcome::draw(Termination::Stalemate));
+            }
+            return Some(Outcome::win(!self.turn, Termination::Checkmate));
+        }
+        let termination = if self.repetitions() >= 5 {
+            Termination::FivefoldRepetition
+        } else if self.halfmove_clock >= 150 {
+            Termination::SeventyFiveMoves
+        } else if self.is_insufficient_material() {
+            Termination::InsufficientMaterial
+        } else {
+            return None;
+        };
+        Some(Outcome::draw(termination))
+    }
+
+    /// Squares figure can move to, not taking care of king safety and
+    /// castling
+    fn figure_targets(
+        &self,
+        kind: usize,
+        player_color: Color,
+        square: Square,
+    ) -> Bitboard {
+        let occupied = self.occupied();
+        let targets = match kind {
+            PAWN => return self.pawn_targets(player_color, square),
+            KNIGHT => knight_attacks(square),
+            BISHOP => bishop_attacks(square, occupied),
+            ROOK => rook_attacks(square, occupied),
+            QUEEN => queen_attacks(square, occupied),
+            _ => king_attacks(square),
+        };
+        targets & !self.players[player_color.index()]
+    }
+
+    fn pawn_targets(&self, player_color: Color, square: Square) -> Bitboard {
+        let (pawn_dir, start_row) = match player_color {
+            White => (-1, 6),
+            Black => (1, 1),
+        };
+        let occupied = self.occupied();
+        let mut enemies = self.players[(!player_color).index()];
+        if let Some(x) = self.en_passant {
+            enemies |= Bitboard::square(x);
+        }
+        let mut result = pawn_attacks(player_color, square) & enemies;
+        if let Some(x) = get_relative_coords(square, RelMov(0, pawn_dir)) {
+            if !occupied.contains(x) {
+                result |= Bitboard::square(x);
+                if let Some(y) =
+                    get_relative_coords(square, RelMov(0, 2 * pawn_dir))
+                {
+                    if !occupied.contains(y) && square.1 == start_row {
+                        result |= Bitboard::square(y);
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.winner, self.termination) {
+            (Some(White), _) => write!(f, "1-0"),
+            (Some(Black), _) => write!(f, "0-1"),
+            // nobody won or lost aborted game
+            (None, Termination::Aborted) => write!(f, "*"),
+            (None, _) => write!(f, "1/2-1/2"),
+        }
+    }
+}
+
+/// Writes move in UCI long algebraic notation, e.g. `e7e8q` or `e1g1`
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let square =
+            |x: Square| convert_to_text_notation(x).map_err(|_| fmt::Error);
+        match *self {
+            Move::Move(from, to) => {
+                write!(f, "{}{}", square(from)?, square(to)?)
+            }
+            Move::Promotion(from, to, promotion) => write!(
+                f,
+                "{}{}{}",
+                square(from)?,
+                square(to)?,
+                promotion.figure(Black)
+            ),
+            Move::Castling(castling) => {
+                let (from, to) = castling.king_squares();
+                write!(f, "{}{}", square(from)?, square(to)?)
+            }
+            Move::Surrender(_) => f.write_str("0000"),
+        }
+    }
+}
+
+impl fmt::Display for BoardState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\n  ")?;
+        for i in 0..8 {
+            write!(f, "{}", (65 + i as u8) as char)?;
+        }
+        writeln!(f)?;
+        for i in 0..8 {
+            write!(f, "{} ", 8 - i)?;
+            for j in 0..8 {
+                write!(f, "{}", self.get(Square(j, i)))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(
+        board: &mut BoardState,
+        from: &str,
+        to: &str,
+    ) -> Result<Option<Outcome>, InvalidMove> {
+        let from = convert_str_to_u8(from).unwrap();
+        let to = convert_str_to_u8(to).unwrap();
+        let color = board.turn();
+        board.validate_move(Move::Move(from, to), color)
+    }
+
+    #[test]
+    fn test_convert_str_to_u8() {
+        let temp = convert_str_to_u8("a8").ok().unwrap();
+        assert_eq!((temp.0, temp.1), (0, 0));
+        let temp = convert_str_to_u8("h1").ok().unwrap();
+        assert_eq!((temp.0, temp.1), (7, 7));
+        let temp = convert_str_to_u8("e2").ok().unwrap();
+        assert_eq!((temp.0, temp.1), (4, 6));
+        let temp = convert_str_to_u8("e4").ok().unwrap();
+        assert_eq!((temp.0, temp.1), (4, 4));
+        let temp = convert_str_to_u8("c7").ok().unwrap();
+        assert_eq!((temp.0, temp.1), (2, 1));
+    }
+
+    #[test]
+    fn test_convert_to_text_notation() {
+        assert_eq!(convert_to_text_notation(Square(0, 0)).ok().unwrap(), "a8");
+        assert_eq!(convert_to_text_notation(Square(7, 7)).ok().unwrap(), "h1");
+        assert_eq!(convert_to_text_notation(Square(4, 4)).ok().unwrap(), "e4");
+    }
+
+    #[test]
+    fn test_get_relative_coords() {
+        assert_eq!(
+            get_relative_coords(Square(4, 6), RelMov(0, -1)),
+            Some(Square(4, 5))
+        );
+    }
+
+    #[test]
+    fn test_make_unmake() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+        ]
+        .iter()
+        {
+            let mut board = BoardState::new();
+            board.parse_fen(fen).unwrap();
+            for player_move in board.legal_moves() {
+                let undo = board.make_move(player_move);
+                assert_ne!(board.export_to_fen(), *fen);
+                assert_eq!(board.plies(), 1);
+                board.unmake_move(undo);
+                assert_eq!(board.export_to_fen(), *fen, "{}", player_move);
+                assert_eq!(board.plies(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hash() {
+        let mut board = BoardState::new();
+        let start = board.hash();
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"].iter() {
+            board.make_move(board.parse_uci(uci).unwrap());
+            assert_eq!(board.hash(), board.full_hash(), "{}", uci);
+        }
+        assert_eq!(board.hash(), start);
+
+        // en passant square nobody can capture on doesn't change position
+        board.make_move(board.parse_uci("e2e4").unwrap());
+        let mut same = BoardState::new();
+        same.parse_fen(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+        )
+        .unwrap();
+        assert_eq!(board.hash(), same.hash());
+
+        let mut board = BoardState::new();
+        board
+            .parse_fen("r3k2r/8/8/8/3pP3/8/8/R3K2R b KQkq e3 0 1")
+            .unwrap();
+        let fen_hash = board.hash();
+        for player_move in board.legal_moves() {
+            let undo = board.make_move(player_move);
+            assert_eq!(board.hash(), board.full_hash(), "{}", player_move);
+            board.unmake_move(undo);
+            assert_eq!(board.hash(), fen_hash);
+        }
+    }
+
+    #[test]
+    fn test_uci() {
+        let mut board = BoardState::new();
+        board
+            .parse_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1")
+            .unwrap();
+        for uci in ["e1g1", "e1c1", "b7a8q", "b7b8n", "a1a8", "e1e2"].iter() {
+            let player_move = board.parse_uci(uci).unwrap();
+            assert!(board.legal_moves().contains(&player_move), "{}", uci);
+            assert_eq!(player_move.to_string(), *uci);
+        }
+        assert_eq!(board.parse_uci("e1g1"), Ok(Move::Castling(Castling::WK)));
+        assert_eq!(
+            board.parse_uci("b7b8k"),
+            Err(ParseUciError::InvalidPromotion)
+        );
+        assert_eq!(board.parse_uci("Nf3"), Err(ParseUciError::InvalidSquare));
+        assert_eq!(
+            board.parse_uci("e2e4e"),
+            Err(ParseUciError::InvalidPromotion)
+        );
+    }
+
+    #[test]
+    fn test_validate_move() {
+        let mut board = BoardState::new();
+        assert_eq!(
+            play(&mut board, "e2", "e5"),
+            Err(InvalidMove::IllegalPattern)
+        );
+        assert_eq!(
+            play(&mut board, "e7", "e5"),
+            Err(InvalidMove::IllegalPattern)
+        );
+        assert_eq!(play(&mut board, "e2", "e4"), Ok(None));
+        assert_eq!(play(&mut board, "e7", "e5"), Ok(None));
+        assert_eq!(
+            play(&mut board, "e1", "e3"),
+            Err(InvalidMove::IllegalPattern)
+        );
+        assert_eq!(play(&mut board, "g1", "f3"), Ok(None));
+        assert_eq!(
+            board.export_to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+    }
+
+    #[test]
+    fn test_promotion() {
+        let mut board = BoardState::new();
+        board.parse_fen("8/4P2p/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        let from = convert_str_to_u8("e7").unwrap();
+        let to = convert_str_to_u8("e8").unwrap();
+        assert_eq!(
+            board.validate_move(Move::Move(from, to), White),
+            Err(InvalidMove::IllegalPattern)
+        );
+        assert_eq!(
+            board.validate_move(
+                Move::Promotion(from, to, Promotion::Knight),
+                White
+            ),
+            Ok(None)
+        );
+        assert_eq!(board.export_to_fen(), "4N3/7p/8/8/8/8/k7/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn test_en_passant() {
+        let mut board = BoardState::new();
+        assert_eq!(play(&mut board, "e2", "e4"), Ok(None));
+        assert_eq!(play(&mut board, "a7", "a6"), Ok(None));
+        assert_eq!(play(&mut board, "e4", "e5"), Ok(None));
+        assert_eq!(play(&mut board, "d7", "d5"), Ok(None));
+        assert_eq!(
+            board.export_to_fen(),
+            "rnbqkbnr/1pp1pppp/p7/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3"
+        );
+        assert_eq!(play(&mut board, "e5", "d6"), Ok(None));
+        assert_eq!(
+            board.export_to_fen(),
+            "rnbqkbnr/1pp1pppp/p2P4/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3"
+        );
+    }
+
+    #[test]
+    fn test_castling() {
+        let mut board = BoardState::new();
+        board
+            .parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")
+            .unwrap();
+        assert_eq!(
+            board.validate_move(Move::Castling(Castling::WK), White),
+            Ok(None)
+        );
+        assert_eq!(board.export_to_fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+        assert_eq!(play(&mut board, "a8", "b8"), Ok(None));
+        assert_eq!(board.export_to_fen(), "1r2k2r/8/8/8/8/8/8/R4RK1 w k - 2 2");
+
+        // king can't castle through attacked square
+        board
+            .parse_fen("r3k2r/8/8/8/8/8/8/R2rK2R w KQkq - 0 1")
+            .unwrap();
+        assert_eq!(
+            board.validate_move(Move::Castling(Castling::WQ), White),
+            Err(InvalidMove::IllegalPattern)
+        );
+        board
+            .parse_fen("r3k2r/8/8/8/8/5r2/8/R3K2R w KQkq - 0 1")
+            .unwrap();
+        assert_eq!(
+            board.validate_move(Move::Castling(Castling::WK), White),
+            Err(InvalidMove::IllegalPattern)
+        );
+        assert_eq!(
+            board.validate_move(Move::Castling(Castling::WQ), White),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_pinned_figure() {
+        let mut board = BoardState::new();
+        board
+            .parse_fen("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1")
+            .unwrap();
+        assert_eq!(play(&mut board, "e2", "d3"), Err(InvalidMove::PiecePinned));
+        assert_eq!(play(&mut board, "e1", "d1"), Ok(None));
+
+        board
+            .parse_fen("4k3/4r3/8/8/8/8/3B4/4K3 w - - 0 1")
+            .unwrap();
+        let from = convert_str_to_u8("d2").unwrap();
+        let to = convert_str_to_u8("e3").unwrap();
+        assert_eq!(
+            board.validate_move(Move::Move(from, to), Black),
+            Err(InvalidMove::NotYourTurn)
+        );
+        assert_eq!(
+            play(&mut board, "d2", "c3"),
+            Err(InvalidMove::LeavesKingInCheck)
+        );
+        assert_eq!(
+            play(&mut board, "e1", "e2"),
+            Err(InvalidMove::LeavesKingInCheck)
+        );
+        assert_eq!(play(&mut board, "d2", "e3"), Ok(None));
+    }
+
+    #[test]
+    fn test_check_mate() {
+        let mut board = BoardState::new();
+        assert_eq!(play(&mut board, "f2", "f3"), Ok(None));
+        assert_eq!(play(&mut board, "e7", "e5"), Ok(None));
+        assert_eq!(play(&mut board, "g2", "g4"), Ok(None));
+        assert_eq!(
+            play(&mut board, "d8", "h4"),
+            Ok(Some(Outcome::win(Black, Termination::Checkmate)))
+        );
+    }
+
+    #[test]
+    fn test_stalemate() {
+        let mut board = BoardState::new();
+        board.parse_fen("7k/8/6Q1/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(
+            play(&mut board, "g6", "f7"),
+            Ok(Some(Outcome::draw(Termination::Stalemate)))
+        );
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut board = BoardState::new();
+        let shuffle = [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")];
+        for (from, to) in shuffle.iter() {
+            assert_eq!(play(&mut board, from, to), Ok(None));
+        }
+        assert_eq!(board.draw_claim(), None);
+        for (from, to) in shuffle.iter() {
+            assert_eq!(play(&mut board, from, to), Ok(None));
+        }
+        assert_eq!(board.draw_claim(), Some(Termination::ThreefoldRepetition));
+        for (from, to) in shuffle.iter().chain(shuffle.iter()).take(7) {
+            assert_eq!(play(&mut board, from, to), Ok(None));
+        }
+        assert_eq!(
+            play(&mut board, "f6", "g8"),
+            Ok(Some(Outcome::draw(Termination::FivefoldRepetition)))
+        );
+    }
+
+    #[test]
+    fn test_move_rules() {
+        let mut board = BoardState::new();
+        board
+            .parse_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80")
+            .unwrap();
+        assert_eq!(board.draw_claim(), None);
+        assert_eq!(play(&mut board, "a1", "a2"), Ok(None));
+        assert_eq!(board.draw_claim(), Some(Termination::FiftyMoves));
+        board
+            .parse_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 149 80")
+            .unwrap();
+        assert_eq!(
+            play(&mut board, "a1", "a2"),
+            Ok(Some(Outcome::draw(Termination::SeventyFiveMoves)))
+        );
+        board
+            .parse_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 149 80")
+            .unwrap();
+        assert_eq!(play(&mut board, "e2", "e4"), Ok(None));
+        assert_eq!(board.draw_claim(), None);
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let mut board = BoardState::new();
+        board.parse_fen("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            play(&mut board, "e1", "d2"),
+            Ok(Some(Outcome::draw(Termination::InsufficientMaterial)))
+        );
+        board.parse_fen("4k3/8/8/8/8/8/3r4/4KN2 w - - 0 1").unwrap();
+        assert_eq!(
+            play(&mut board, "e1", "d2"),
+            Ok(Some(Outcome::draw(Termination::InsufficientMaterial)))
+        );
+        board
+            .parse_fen("4kb2/8/8/8/8/8/3r4/4KB2 w - - 0 1")
+            .unwrap();
+        assert_eq!(play(&mut board, "e1", "d2"), Ok(None));
+        board
+            .parse_fen("4k1b1/8/8/8/8/8/3r4/4KB2 w - - 0 1")
+            .unwrap();
+        assert_eq!(
+            play(&mut board, "e1", "d2"),
+            Ok(Some(Outcome::draw(Termination::InsufficientMaterial)))
+        );
+        board
+            .parse_fen("4k1n1/8/8/8/8/8/3r4/4KN2 w - - 0 1")
+            .unwrap();
+        assert_eq!(play(&mut board, "e1", "d2"), Ok(None));
+    }
+}
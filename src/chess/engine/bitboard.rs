@@ -0,0 +1,285 @@
+//! Sets of squares packed into 64 bit numbers, bit 0 is a1, bit 7 is h1
+//! and bit 63 is h8, with attack tables computed at compile time
+
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not};
+
+use super::{Color, Square};
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+
+    pub fn square(square: Square) -> Bitboard {
+        Bitboard(1 << index(square))
+    }
+
+    pub fn contains(self, square: Square) -> bool {
+        self.0 & (1 << index(square)) != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn first(self) -> Option<Square> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(from_index(self.0.trailing_zeros() as usize))
+        }
+    }
+}
+
+/// Iterates over squares of the set, from a1 to h8
+impl Iterator for Bitboard {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        let square = self.first()?;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+
+    fn bitand(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 & other.0)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Bitboard;
+
+    fn bitor(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 | other.0)
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Bitboard;
+
+    fn bitxor(self, other: Bitboard) -> Bitboard {
+        Bitboard(self.0 ^ other.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, other: Bitboard) {
+        self.0 &= other.0;
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, other: Bitboard) {
+        self.0 |= other.0;
+    }
+}
+
+/// Index of the bit of square, board coordinates start from a8
+pub fn index(square: Square) -> usize {
+    (7 - square.1 as usize) * 8 + square.0 as usize
+}
+
+pub fn from_index(index: usize) -> Square {
+    Square((index % 8) as u8, 7 - (index / 8) as u8)
+}
+
+/// Whole rank, 1 is the first one
+pub const fn rank(rank: u8) -> Bitboard {
+    Bitboard(0xff << (8 * (rank - 1)))
+}
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+const KING_STEPS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+// directions of sliding figures as (file, rank) steps, ones increasing
+// bit index go first
+const DIRECTIONS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (-1, 1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (1, -1),
+];
+
+const ROOK_DIRECTIONS: [usize; 4] = [0, 2, 4, 6];
+const BISHOP_DIRECTIONS: [usize; 4] = [1, 3, 5, 7];
+
+/// Squares reachable by single steps from every square
+const fn step_table(steps: &[(i8, i8)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let (file, rank) = ((square % 8) as i8, (square / 8) as i8);
+        let mut i = 0;
+        while i < steps.len() {
+            let (x, y) = (file + steps[i].0, rank + steps[i].1);
+            if x >= 0 && x < 8 && y >= 0 && y < 8 {
+                table[square] |= 1 << (y * 8 + x);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+/// Squares from every square to the edge of the board in every direction
+const fn ray_table() -> [[u64; 64]; 8] {
+    let mut table = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let (dx, dy) = DIRECTIONS[direction];
+        let mut square = 0;
+        while square < 64 {
+            let (mut x, mut y) = ((square % 8) as i8, (square / 8) as i8);
+            loop {
+                x += dx;
+                y += dy;
+                if x < 0 || x >= 8 || y < 0 || y >= 8 {
+                    break;
+                }
+                table[direction][square] |= 1 << (y * 8 + x);
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    table
+}
+
+const KNIGHT_ATTACKS: [u64; 64] = step_table(&KNIGHT_STEPS);
+const KING_ATTACKS: [u64; 64] = step_table(&KING_STEPS);
+const WHITE_PAWN_ATTACKS: [u64; 64] = step_table(&[(-1, 1), (1, 1)]);
+const BLACK_PAWN_ATTACKS: [u64; 64] = step_table(&[(-1, -1), (1, -1)]);
+const RAYS: [[u64; 64]; 8] = ray_table();
+
+pub fn knight_attacks(square: Square) -> Bitboard {
+    Bitboard(KNIGHT_ATTACKS[index(square)])
+}
+
+pub fn king_attacks(square: Square) -> Bitboard {
+    Bitboard(KING_ATTACKS[index(square)])
+}
+
+/// Squares pawn of given color standing on square attacks
+pub fn pawn_attacks(color: Color, square: Square) -> Bitboard {
+    match color {
+        Color::White => Bitboard(WHITE_PAWN_ATTACKS[index(square)]),
+        Color::Black => Bitboard(BLACK_PAWN_ATTACKS[index(square)]),
+    }
+}
+
+/// Ray in given direction up to and including the first occupied square
+fn ray_attacks(direction: usize, from: usize, occupied: u64) -> u64 {
+    let ray = RAYS[direction][from];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    let blocker = if direction < 4 {
+        blockers.trailing_zeros() as usize
+    } else {
+        63 - blockers.leading_zeros() as usize
+    };
+    ray ^ RAYS[direction][blocker]
+}
+
+fn slider_attacks(
+    directions: &[usize],
+    square: Square,
+    occupied: Bitboard,
+) -> Bitboard {
+    let from = index(square);
+    Bitboard(
+        directions
+            .iter()
+            .fold(0, |acc, x| acc | ray_attacks(*x, from, occupied.0)),
+    )
+}
+
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    slider_attacks(&BISHOP_DIRECTIONS, square, occupied)
+}
+
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    slider_attacks(&ROOK_DIRECTIONS, square, occupied)
+}
+
+pub fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(square, occupied) | rook_attacks(square, occupied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::engine::{convert_str_to_u8, convert_to_text_notation};
+
+    fn squares(board: Bitboard) -> Vec<String> {
+        board
+            .map(|x| convert_to_text_notation(x).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_attacks() {
+        let square = |x| convert_str_to_u8(x).unwrap();
+        assert_eq!(index(square("a1")), 0);
+        assert_eq!(index(square("h8")), 63);
+        assert_eq!(from_index(12), square("e2"));
+        assert_eq!(squares(knight_attacks(square("a1"))), ["c2", "b3"]);
+        assert_eq!(squares(pawn_attacks(Color::Black, square("a7"))), ["b6"]);
+        let occupied = Bitboard::square(square("d4"))
+            | Bitboard::square(square("d7"))
+            | Bitboard::square(square("b2"));
+        assert_eq!(
+            squares(rook_attacks(square("d2"), occupied)),
+            ["d1", "b2", "c2", "e2", "f2", "g2", "h2", "d3", "d4"]
+        );
+        assert_eq!(
+            squares(bishop_attacks(square("d6"), occupied)),
+            [
+                "h2", "a3", "g3", "b4", "f4", "c5", "e5", "c7", "e7", "b8",
+                "f8"
+            ]
+        );
+    }
+}
@@ -0,0 +1,418 @@
+//! Forsyth–Edwards Notation, e.g.
+//! `rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1`
+
+use super::*;
+
+/// Reason FEN can't be parsed, ranks are numbered from 1 to 8
+#[derive(Debug, PartialEq)]
+pub enum ParseFenError {
+    /// FEN doesn't have 6 fields separated by single spaces
+    FieldCount(usize),
+    RankCount(usize),
+    /// Rank has unknown figure, zero or adjacent digits, or not 8 squares
+    InvalidRank(u8),
+    PawnOnBackRank(u8),
+    KingCount(Color, usize),
+    /// More figures than promotions of pawns could give
+    TooManyFigures(Color),
+    /// Player who has just moved is left in check
+    OpponentInCheck,
+    InvalidTurn,
+    /// Castling is not `-` or some of `KQkq` in this order
+    InvalidCastling,
+    /// King or rook of castling right has left its initial square
+    ImpossibleCastling(char),
+    InvalidEnPassant,
+    /// En passant square doesn't follow double pawn push
+    ImpossibleEnPassant,
+    InvalidHalfmoveClock,
+    InvalidFullmoves,
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        White => "white",
+        Black => "black",
+    }
+}
+
+impl fmt::Display for ParseFenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ParseFenError::*;
+        match self {
+            FieldCount(x) => write!(f, "expected 6 fields, found {}", x),
+            RankCount(x) => {
+                write!(f, "placement: expected 8 ranks, found {}", x)
+            }
+            InvalidRank(x) => write!(f, "placement: rank {} is invalid", x),
+            PawnOnBackRank(x) => {
+                write!(f, "placement: pawn on rank {}", x)
+            }
+            KingCount(color, x) => write!(
+                f,
+                "placement: {} has {} kings instead of one",
+                color_name(*color),
+                x
+            ),
+            TooManyFigures(color) => write!(
+                f,
+                "placement: {} has too many figures",
+                color_name(*color)
+            ),
+            OpponentInCheck => {
+                write!(f, "placement: side not to move is in check")
+            }
+            InvalidTurn => write!(f, "side to move: expected w or b"),
+            InvalidCastling => write!(f, "castling: expected - or KQkq"),
+            ImpossibleCastling(x) => {
+                write!(f, "castling: king or rook of {} has left its square", x)
+            }
+            InvalidEnPassant => write!(f, "en passant: expected - or square"),
+            ImpossibleEnPassant => {
+                write!(f, "en passant: no double pawn push to capture")
+            }
+            InvalidHalfmoveClock => write!(f, "halfmove clock: not a number"),
+            InvalidFullmoves => {
+                write!(f, "fullmove number: not a positive number")
+            }
+        }
+    }
+}
+
+/// Reads non-negative number without sign or leading zeros, so that
+/// it is written back the same way
+fn parse_number(text: &str) -> Option<u32> {
+    if text.is_empty()
+        || !text.bytes().all(|x| x.is_ascii_digit())
+        || (text.len() > 1 && text.starts_with('0'))
+    {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn parse_placement(text: &str) -> Result<[[char; 8]; 8], ParseFenError> {
+    let ranks: Vec<&str> = text.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(ParseFenError::RankCount(ranks.len()));
+    }
+    let mut board = [['.'; 8]; 8];
+    for (y, rank) in ranks.into_iter().enumerate() {
+        let invalid = ParseFenError::InvalidRank(8 - y as u8);
+        let mut x = 0;
+        let mut after_digit = false;
+        for chr in rank.chars() {
+            match chr {
+                '1'..='8' if !after_digit => {
+                    x += chr.to_digit(10).unwrap() as usize;
+                    after_digit = true;
+                }
+                'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R'
+                | 'Q' | 'K'
+                    if x < 8 =>
+                {
+                    board[x][y] = chr;
+                    x += 1;
+                    after_digit = false;
+                }
+                _ => return Err(invalid),
+            }
+            if x > 8 {
+                return Err(invalid);
+            }
+        }
+        if x != 8 {
+            return Err(invalid);
+        }
+    }
+    Ok(board)
+}
+
+fn parse_castling(text: &str) -> Result<CastlingRights, ParseFenError> {
+    let rights = CastlingRights {
+        wk: text.contains('K'),
+        wq: text.contains('Q'),
+        bk: text.contains('k'),
+        bq: text.contains('q'),
+    };
+    if text == "-" || (!text.is_empty() && castling_str(rights) == text) {
+        Ok(rights)
+    } else {
+        Err(ParseFenError::InvalidCastling)
+    }
+}
+
+fn castling_str(rights: CastlingRights) -> String {
+    let mut result: String = [
+        (rights.wk, 'K'),
+        (rights.wq, 'Q'),
+        (rights.bk, 'k'),
+        (rights.bq, 'q'),
+    ]
+    .iter()
+    .filter(|x| x.0)
+    .map(|x| x.1)
+    .collect();
+    if result.is_empty() {
+        result.push('-');
+    }
+    result
+}
+
+impl BoardState {
+    /// Deserialization: parses Forsyth–Edwards Notation into BoardState,
+    /// on error the board is left untouched. Every accepted FEN is
+    /// written back unchanged by `export_to_fen`
+    pub fn parse_fen(&mut self, fen_str: &str) -> Result<(), ParseFenError> {
+        let fields: Vec<&str> = fen_str.split(' ').collect();
+        if fields.len() != 6 {
+            return Err(ParseFenError::FieldCount(fields.len()));
+        }
+        let en_passant = match fields[3] {
+            "-" => None,
+            x if x.len() == 2 && x.starts_with(char::is_lowercase) => Some(
+                convert_str_to_u8(x)
+                    .map_err(|_| ParseFenError::InvalidEnPassant)?,
+            ),
+            _ => return Err(ParseFenError::InvalidEnPassant),
+        };
+        let placement = parse_placement(fields[0])?;
+        let mut board = BoardState {
+            figures: [Bitboard::EMPTY; 6],
+            players: [Bitboard::EMPTY; 2],
+            turn: match fields[1] {
+                "w" => White,
+                "b" => Black,
+                _ => return Err(ParseFenError::InvalidTurn),
+            },
+            castling_rights: parse_castling(fields[2])?,
+            en_passant,
+            halfmove_clock: parse_number(fields[4])
+                .ok_or(ParseFenError::InvalidHalfmoveClock)?,
+            fullmoves: parse_number(fields[5])
+                .filter(|x| *x > 0)
+                .ok_or(ParseFenError::InvalidFullmoves)?,
+            hash: 0,
+            history: Vec::new(),
+        };
+        board.set_placement(&placement);
+        board.validate_position()?;
+        board.hash = board.full_hash();
+        board.history.push(board.hash);
+        *self = board;
+        Ok(())
+    }
+
+    pub fn export_to_fen(&self) -> String {
+        let mut board_str = String::new();
+        for y in 0..8 {
+            let mut counter = 0;
+            for x in 0..8 {
+                let figure = self.get(Square(x, y));
+                if figure == '.' {
+                    counter += 1;
+                } else {
+                    if counter > 0 {
+                        board_str.push_str(&counter.to_string());
+                        counter = 0;
+                    }
+                    board_str.push(figure);
+                }
+            }
+            if counter > 0 {
+                board_str.push_str(&counter.to_string());
+            }
+            if y != 7 {
+                board_str.push('/');
+            }
+        }
+
+        board_str.push(' ');
+
+        let color = match self.turn {
+            Black => 'b',
+            White => 'w',
+        };
+        board_str.push(color);
+
+        board_str.push(' ');
+
+        board_str.push_str(&castling_str(self.castling_rights));
+
+        board_str.push(' ');
+
+        match self.en_passant {
+            // due to way it is created inner state can not be invalid, or it would have returned error before
+            Some(x) => {
+                board_str.push_str(&convert_to_text_notation(x).unwrap())
+            }
+            None => board_str.push('-'),
+        }
+
+        board_str.push(' ');
+
+        board_str.push_str(&self.halfmove_clock.to_string());
+
+        board_str.push(' ');
+
+        board_str.push_str(&self.fullmoves.to_string());
+        board_str
+    }
+
+    /// Checks that position could arise in a game, so the engine can
+    /// rely on both kings being present and pawns having moves
+    fn validate_position(&self) -> Result<(), ParseFenError> {
+        for &rank in [1, 8].iter() {
+            if !(self.figures[PAWN] & bitboard::rank(rank)).is_empty() {
+                return Err(ParseFenError::PawnOnBackRank(rank));
+            }
+        }
+
+        for &color in [White, Black].iter() {
+            let count = |kind| self.pieces(kind, color).count() as usize;
+            let kings = count(KING);
+            if kings != 1 {
+                return Err(ParseFenError::KingCount(color, kings));
+            }
+            let promoted = count(QUEEN).saturating_sub(1)
+                + count(ROOK).saturating_sub(2)
+                + count(BISHOP).saturating_sub(2)
+                + count(KNIGHT).saturating_sub(2);
+            if count(PAWN) + promoted > 8 {
+                return Err(ParseFenError::TooManyFigures(color));
+            }
+        }
+
+        if let Some(king) = self.get_king(!self.turn) {
+            if self.is_attacked(king, self.turn) {
+                return Err(ParseFenError::OpponentInCheck);
+            }
+        }
+
+        let castlings = [
+            (self.castling_rights.wk, Castling::WK, 'K', 7),
+            (self.castling_rights.wq, Castling::WQ, 'Q', 0),
+            (self.castling_rights.bk, Castling::BK, 'k', 7),
+            (self.castling_rights.bq, Castling::BQ, 'q', 0),
+        ];
+        for &(right, castling, name, rook_x) in castlings.iter() {
+            let (king, _) = castling.king_squares();
+            let rook = Square(rook_x, king.1);
+            let (king_figure, rook_figure) = if name.is_uppercase() {
+                ('K', 'R')
+            } else {
+                ('k', 'r')
+            };
+            if right
+                && (self.get(king) != king_figure
+                    || self.get(rook) != rook_figure)
+            {
+                return Err(ParseFenError::ImpossibleCastling(name));
+            }
+        }
+
+        if let Some(square) = self.en_passant {
+            // pawn of player who has just moved went from `from` to `to`
+            let (row, pawn, dir) = match self.turn {
+                White => (2, 'p', 1),
+                Black => (5, 'P', -1),
+            };
+            let from = get_relative_coords(square, RelMov(0, -dir));
+            let to = get_relative_coords(square, RelMov(0, dir));
+            let pushed = match (from, to) {
+                (Some(from), Some(to)) => {
+                    square.1 == row
+                        && self.get(square) == '.'
+                        && self.get(from) == '.'
+                        && self.get(to) == pawn
+                }
+                _ => false,
+            };
+            if !pushed || self.halfmove_clock != 0 {
+                return Err(ParseFenError::ImpossibleEnPassant);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(fen: &str) -> Result<String, ParseFenError> {
+        let mut board = BoardState::new();
+        board.parse_fen(fen).map(|_| board.export_to_fen())
+    }
+
+    #[test]
+    fn test_fen_round_trip() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(BoardState::new().export_to_fen(), start);
+
+        for fen in &[
+            start,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b Kq - 3 17",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "8/8/8/K2pP2r/8/8/8/7k w - d6 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            assert_eq!(parse(fen).as_deref(), Ok(*fen));
+        }
+    }
+
+    #[test]
+    fn test_fen_syntax() {
+        use ParseFenError::*;
+        let cases = [
+            ("8/8/8/8/8/8/8/K6k w - - 0", FieldCount(5)),
+            ("8/8/8/8/8/8/8/K6k  w - - 0 1", FieldCount(7)),
+            ("8/8/8/8/8/8/K6k w - - 0 1", RankCount(7)),
+            ("8/8/8/8/8/8/8/K5k w - - 0 1", InvalidRank(1)),
+            ("8/8/8/8/8/8/8/K33k w - - 0 1", InvalidRank(1)),
+            ("8/8/x7/8/8/8/8/K6k w - - 0 1", InvalidRank(6)),
+            ("8/8/8/8/8/8/8/K6k W - - 0 1", InvalidTurn),
+            ("8/8/8/8/8/8/8/K6k w qk - 0 1", InvalidCastling),
+            ("8/8/8/8/8/8/8/K6k w - e9 0 1", InvalidEnPassant),
+            ("8/8/8/8/8/8/8/K6k w - - 01 1", InvalidHalfmoveClock),
+            ("8/8/8/8/8/8/8/K6k w - - -1 1", InvalidHalfmoveClock),
+            ("8/8/8/8/8/8/8/K6k w - - 0 0", InvalidFullmoves),
+        ];
+        for (fen, err) in cases.iter() {
+            assert_eq!(parse(fen).as_ref(), Err(err), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_fen_illegal_position() {
+        use ParseFenError::*;
+        let cases = [
+            ("8/8/8/8/8/8/8/K7 w - - 0 1", KingCount(Black, 0)),
+            ("k7/8/8/8/8/8/8/KK6 w - - 0 1", KingCount(White, 2)),
+            ("k7/8/8/8/8/8/8/K5P1 w - - 0 1", PawnOnBackRank(1)),
+            ("k6p/8/8/8/8/8/8/K7 w - - 0 1", PawnOnBackRank(8)),
+            (
+                "k7/8/8/8/8/PPPPPPPP/8/KQQ5 w - - 0 1",
+                TooManyFigures(White),
+            ),
+            ("k6R/8/8/8/8/8/8/K7 w - - 0 1", OpponentInCheck),
+            ("4k3/8/8/8/8/8/8/4K2R w KQ - 0 1", ImpossibleCastling('Q')),
+            ("r3k3/8/8/8/8/8/8/4K3 w kq - 0 1", ImpossibleCastling('k')),
+            ("4k3/8/8/8/4P3/8/8/4K3 b - e4 0 1", ImpossibleEnPassant),
+            ("4k3/8/8/8/4P3/8/8/4K3 w - e3 0 1", ImpossibleEnPassant),
+            ("4k3/8/8/8/4P3/8/8/4K3 b - e3 5 1", ImpossibleEnPassant),
+            ("4k3/8/8/8/4P3/8/4P3/4K3 b - e3 0 1", ImpossibleEnPassant),
+        ];
+        for (fen, err) in cases.iter() {
+            assert_eq!(parse(fen).as_ref(), Err(err), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_fen_error_keeps_board() {
+        let mut board = BoardState::new();
+        let start = board.export_to_fen();
+        assert!(board.parse_fen("8/8/8/8/8/8/8/K7 w - - 0 1").is_err());
+        assert_eq!(board.export_to_fen(), start);
+    }
+}
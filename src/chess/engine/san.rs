@@ -0,0 +1,380 @@
+//! Standard Algebraic Notation, e.g. `Nbd7`, `exd6`, `O-O-O`, `e8=Q+`
+
+use std::str::FromStr;
+
+use super::*;
+
+/// Reason SAN can't be parsed
+#[derive(Debug, PartialEq)]
+pub enum ParseSanError {
+    /// String is not SAN at all
+    InvalidSyntax,
+    IllegalMove,
+    /// Several legal moves match, e.g. `Nd7` when both knights can go there
+    AmbiguousMove,
+}
+
+fn file_char(square: Square) -> char {
+    (b'a' + square.0) as char
+}
+
+fn rank_char(square: Square) -> char {
+    (b'8' - square.1) as char
+}
+
+impl BoardState {
+    /// Writes legal move in SAN, with check or mate suffix
+    /// # Returns
+    /// * `None` - move is not legal in current position
+    pub fn to_san(&self, player_move: Move) -> Option<String> {
+        let moves = self.legal_moves();
+        if !moves.contains(&player_move) {
+            return None;
+        }
+        let mut san = String::with_capacity(8);
+        match player_move {
+            Move::Castling(Castling::WK) | Move::Castling(Castling::BK) => {
+                san.push_str("O-O")
+            }
+            Move::Castling(_) => san.push_str("O-O-O"),
+            Move::Move(from, to) | Move::Promotion(from, to, _) => {
+                let figure = self.get(from);
+                let is_pawn = figure.eq_ignore_ascii_case(&'P');
+                let is_capture = self.get(to) != '.'
+                    || (is_pawn && Some(to) == self.en_passant);
+                if is_pawn {
+                    if is_capture {
+                        san.push(file_char(from));
+                    }
+                } else {
+                    san.push(figure.to_ascii_uppercase());
+                    let rivals: Vec<Square> = moves
+                        .iter()
+                        .filter_map(|x| match x {
+                            Move::Move(rival, rival_to)
+                                if *rival_to == to
+                                    && *rival != from
+                                    && self.get(*rival) == figure =>
+                            {
+                                Some(*rival)
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    if !rivals.is_empty() {
+                        let file_unique = rivals.iter().all(|x| x.0 != from.0);
+                        let rank_unique = rivals.iter().all(|x| x.1 != from.1);
+                        if file_unique || !rank_unique {
+                            san.push(file_char(from));
+                        }
+                        if !file_unique {
+                            san.push(rank_char(from));
+                        }
+                    }
+                }
+                if is_capture {
+                    san.push('x');
+                }
+                san.push(file_char(to));
+                san.push(rank_char(to));
+                if let Move::Promotion(_, _, promotion) = player_move {
+                    san.push('=');
+                    san.push(promotion.figure(White));
+                }
+            }
+            Move::Surrender(_) => return None,
+        }
+        let mut board = self.without_history();
+        board.commit_move(player_move);
+        if board.is_check() {
+            if board.legal_moves().is_empty() {
+                san.push('#');
+            } else {
+                san.push('+');
+            }
+        }
+        Some(san)
+    }
+
+    /// Finds legal move written in SAN, see `San` for accepted syntax
+    pub fn parse_san(&self, san: &str) -> Result<Move, ParseSanError> {
+        self.find_san(san.parse()?)
+    }
+
+    /// Finds legal move matching parsed SAN
+    pub fn find_san(&self, san: San) -> Result<Move, ParseSanError> {
+        let moves = self.legal_moves();
+        let (figure, file, rank, to, promotion) = match san {
+            San::CastleShort | San::CastleLong => {
+                let castling = match (san, self.turn) {
+                    (San::CastleShort, White) => Castling::WK,
+                    (San::CastleShort, Black) => Castling::BK,
+                    (_, White) => Castling::WQ,
+                    (_, Black) => Castling::BQ,
+                };
+                let castling = Move::Castling(castling);
+                if !moves.contains(&castling) {
+                    return Err(ParseSanError::IllegalMove);
+                }
+                return Ok(castling);
+            }
+            San::Move {
+                figure,
+                file,
+                rank,
+                to,
+                promotion,
+                ..
+            } => (figure, file, rank, to, promotion),
+        };
+        let mut found = moves.into_iter().filter(|x| {
+            let (from, move_to, move_promotion) = match x {
+                Move::Move(from, move_to) => (*from, *move_to, None),
+                Move::Promotion(from, move_to, promotion) => {
+                    (*from, *move_to, Some(*promotion))
+                }
+                _ => return false,
+            };
+            move_to == to
+                && move_promotion == promotion
+                && self.get(from).to_ascii_uppercase() == figure
+                && file.map_or(true, |x| x == file_char(from))
+                && rank.map_or(true, |x| x == rank_char(from))
+        });
+        match (found.next(), found.next()) {
+            (Some(x), None) => Ok(x),
+            (Some(_), Some(_)) => Err(ParseSanError::AmbiguousMove),
+            (None, _) => Err(ParseSanError::IllegalMove),
+        }
+    }
+}
+
+/// Move in SAN as written by player, without board it is not known
+/// which move it is. Check and mate suffixes, annotations like `!?` and
+/// `e.p.` are accepted but not verified, capture mark is optional
+/// * `CastleShort` - `O-O`
+/// * `CastleLong` - `O-O-O`
+/// * `Move` - figure letter, `P` for pawns, optional file and rank of
+///   the figure, capture mark, destination and promotion, e.g. `Nbxd7`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum San {
+    CastleShort,
+    CastleLong,
+    Move {
+        figure: char,
+        file: Option<char>,
+        rank: Option<char>,
+        capture: bool,
+        to: Square,
+        promotion: Option<Promotion>,
+    },
+}
+
+impl FromStr for San {
+    type Err = ParseSanError;
+    fn from_str(san: &str) -> Result<Self, Self::Err> {
+        let san = san.trim();
+        let san = san.strip_suffix("e.p.").unwrap_or(san).trim_end();
+        let san = san.trim_end_matches(|c| "+#!?".contains(c));
+        match san {
+            "O-O" | "0-0" => return Ok(San::CastleShort),
+            "O-O-O" | "0-0-0" => return Ok(San::CastleLong),
+            _ => {}
+        }
+        if !san.is_ascii() || san.len() < 2 {
+            return Err(ParseSanError::InvalidSyntax);
+        }
+        let (figure, rest) = match san.as_bytes()[0] {
+            x @ b'K' | x @ b'Q' | x @ b'R' | x @ b'B' | x @ b'N' => {
+                (x as char, &san[1..])
+            }
+            _ => ('P', san),
+        };
+        let (rest, promotion) = match rest.chars().last() {
+            Some(x) if figure == 'P' && "QRBN".contains(x) => {
+                let rest = &rest[..rest.len() - 1];
+                let promotion =
+                    PROMOTIONS.iter().find(|p| p.figure(White) == x).copied();
+                (rest.strip_suffix('=').unwrap_or(rest), promotion)
+            }
+            _ => (rest, None),
+        };
+        if rest.len() < 2 {
+            return Err(ParseSanError::InvalidSyntax);
+        }
+        let (hint, to) = rest.split_at(rest.len() - 2);
+        let to =
+            convert_str_to_u8(to).map_err(|_| ParseSanError::InvalidSyntax)?;
+        let capture = hint.ends_with('x');
+        let hint = hint.strip_suffix('x').unwrap_or(hint);
+        let mut file = None;
+        let mut rank = None;
+        for x in hint.chars() {
+            match x {
+                'a'..='h' if file.is_none() && rank.is_none() => file = Some(x),
+                '1'..='8' if rank.is_none() => rank = Some(x),
+                _ => return Err(ParseSanError::InvalidSyntax),
+            }
+        }
+        // pawns are told apart only by file
+        if figure == 'P' && rank.is_some() {
+            return Err(ParseSanError::InvalidSyntax);
+        }
+        Ok(San::Move {
+            figure,
+            file,
+            rank,
+            capture,
+            to,
+            promotion,
+        })
+    }
+}
+
+impl fmt::Display for San {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            San::CastleShort => write!(f, "O-O"),
+            San::CastleLong => write!(f, "O-O-O"),
+            San::Move {
+                figure,
+                file,
+                rank,
+                capture,
+                to,
+                promotion,
+            } => {
+                if *figure != 'P' {
+                    write!(f, "{}", figure)?;
+                }
+                for x in file.iter().chain(rank.iter()) {
+                    write!(f, "{}", x)?;
+                }
+                if *capture {
+                    write!(f, "x")?;
+                }
+                write!(f, "{}{}", file_char(*to), rank_char(*to))?;
+                match promotion {
+                    Some(x) => write!(f, "={}", x.figure(White)),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_fen(fen: &str) -> BoardState {
+        let mut board = BoardState::new();
+        board.parse_fen(fen).unwrap();
+        board
+    }
+
+    fn square(text: &str) -> Square {
+        convert_str_to_u8(text).unwrap()
+    }
+
+    #[test]
+    fn test_to_san() {
+        let board = BoardState::new();
+        let e4 = Move::Move(square("e2"), square("e4"));
+        assert_eq!(board.to_san(e4).unwrap(), "e4");
+        let nf3 = Move::Move(square("g1"), square("f3"));
+        assert_eq!(board.to_san(nf3).unwrap(), "Nf3");
+        assert_eq!(board.to_san(Move::Move(square("e2"), square("e5"))), None);
+
+        // en passant capture and long castling
+        let board = from_fen("r3k2r/8/5n2/3pP3/8/8/8/R3K1NR w KQkq d6 0 1");
+        let exd6 = Move::Move(square("e5"), square("d6"));
+        assert_eq!(board.to_san(exd6).unwrap(), "exd6");
+        let castling = Move::Castling(Castling::WQ);
+        assert_eq!(board.to_san(castling).unwrap(), "O-O-O");
+        // knights on b8 and f6 can both go to d7
+        let board = from_fen("1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1");
+        let nbd7 = Move::Move(square("b8"), square("d7"));
+        assert_eq!(board.to_san(nbd7).unwrap(), "Nbd7");
+        let board = from_fen("4k3/8/5n2/8/5n2/8/8/4K3 b - - 0 1");
+        let n6d5 = Move::Move(square("f6"), square("d5"));
+        assert_eq!(board.to_san(n6d5).unwrap(), "N6d5");
+
+        let board = from_fen("8/4P3/8/8/8/8/k7/4K3 w - - 0 1");
+        let e8q = Move::Promotion(square("e7"), square("e8"), Promotion::Queen);
+        assert_eq!(board.to_san(e8q).unwrap(), "e8=Q");
+        let board = from_fen("4k3/1K2P3/8/8/8/8/8/8 w - - 0 1");
+        let e8q = Move::Promotion(square("e7"), square("e8"), Promotion::Queen);
+        assert_eq!(board.to_san(e8q), None);
+
+        let board = from_fen(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        );
+        let qxf7 = Move::Move(square("f3"), square("f7"));
+        assert_eq!(board.to_san(qxf7).unwrap(), "Qxf7#");
+        let bxf7 = Move::Move(square("c4"), square("f7"));
+        assert_eq!(board.to_san(bxf7).unwrap(), "Bxf7+");
+    }
+
+    #[test]
+    fn test_parse_san() {
+        let board = BoardState::new();
+        let e4 = Move::Move(square("e2"), square("e4"));
+        assert_eq!(board.parse_san("e4"), Ok(e4));
+        assert_eq!(board.parse_san("e5"), Err(ParseSanError::IllegalMove));
+        assert_eq!(board.parse_san("O-O"), Err(ParseSanError::IllegalMove));
+        assert_eq!(board.parse_san("Zz9"), Err(ParseSanError::InvalidSyntax));
+
+        let board = from_fen("r3k2r/8/5n2/3pP3/8/8/8/R3K1NR w KQkq d6 0 1");
+        let exd6 = Move::Move(square("e5"), square("d6"));
+        assert_eq!(board.parse_san("exd6 e.p."), Ok(exd6));
+        assert_eq!(board.parse_san("exd6"), Ok(exd6));
+        let castling = Move::Castling(Castling::WQ);
+        assert_eq!(board.parse_san("O-O-O"), Ok(castling));
+
+        // knights on b8 and f6 can both go to d7
+        let board = from_fen("1n2k3/8/5n2/8/8/8/8/4K3 b - - 0 1");
+        let nbd7 = Move::Move(square("b8"), square("d7"));
+        assert_eq!(board.parse_san("Nbd7"), Ok(nbd7));
+        assert_eq!(board.parse_san("Nd7"), Err(ParseSanError::AmbiguousMove));
+
+        let board = from_fen("4k3/4P3/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(board.parse_san("e8=Q+"), Err(ParseSanError::IllegalMove));
+        let board = from_fen("2k5/4P3/8/8/8/8/8/K7 w - - 0 1");
+        let e8q = Move::Promotion(square("e7"), square("e8"), Promotion::Queen);
+        assert_eq!(board.parse_san("e8=Q+"), Ok(e8q));
+        assert_eq!(board.parse_san("e8Q"), Ok(e8q));
+        assert_eq!(board.parse_san("e8"), Err(ParseSanError::IllegalMove));
+
+        let board = from_fen(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        );
+        let qxf7 = Move::Move(square("f3"), square("f7"));
+        assert_eq!(board.parse_san("Qxf7#"), Ok(qxf7));
+    }
+
+    #[test]
+    fn test_san_syntax() {
+        for x in
+            ["Nbd7", "exd6", "O-O-O", "e8=Q", "Qxf7", "R1a3", "Qh4xe1"].iter()
+        {
+            assert_eq!(x.parse::<San>().unwrap().to_string(), *x);
+        }
+        assert_eq!("e8Q+".parse::<San>().unwrap().to_string(), "e8=Q");
+        assert_eq!("0-0".parse::<San>(), Ok(San::CastleShort));
+        assert_eq!("Kk1".parse::<San>(), Err(ParseSanError::InvalidSyntax));
+        assert_eq!("e".parse::<San>(), Err(ParseSanError::InvalidSyntax));
+        assert_eq!("1e4".parse::<San>(), Err(ParseSanError::InvalidSyntax));
+    }
+
+    #[test]
+    fn test_san_round_trip() {
+        let board = from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        );
+        for x in board.legal_moves() {
+            let san = board.to_san(x).unwrap();
+            assert_eq!(board.parse_san(&san), Ok(x), "{}", san);
+        }
+    }
+}
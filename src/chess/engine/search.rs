@@ -0,0 +1,571 @@
+//! Search for the best move: iterative deepening alpha-beta with
+//! quiescence search, move ordering and transposition table
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::*;
+
+const INFINITY: i32 = 1_000_000;
+const MATE: i32 = 100_000;
+const MAX_PLY: usize = 128;
+
+/// Depth searched when limits don't say otherwise
+pub const MAX_DEPTH: u32 = 64;
+
+// how often time and stop flag are checked, in nodes
+const CHECK_EVERY: u64 = 1024;
+
+const VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+
+// bonuses of figure on square, from white side with a8 first
+#[rustfmt::skip]
+const SQUARE_BONUS: [[i32; 64]; 6] = [
+    [
+         0,   0,   0,   0,   0,   0,   0,   0,
+        50,  50,  50,  50,  50,  50,  50,  50,
+        10,  10,  20,  30,  30,  20,  10,  10,
+         5,   5,  10,  25,  25,  10,   5,   5,
+         0,   0,   0,  20,  20,   0,   0,   0,
+         5,  -5, -10,   0,   0, -10,  -5,   5,
+         5,  10,  10, -20, -20,  10,  10,   5,
+         0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    [
+       -50, -40, -30, -30, -30, -30, -40, -50,
+       -40, -20,   0,   0,   0,   0, -20, -40,
+       -30,   0,  10,  15,  15,  10,   0, -30,
+       -30,   5,  15,  20,  20,  15,   5, -30,
+       -30,   0,  15,  20,  20,  15,   0, -30,
+       -30,   5,  10,  15,  15,  10,   5, -30,
+       -40, -20,   0,   5,   5,   0, -20, -40,
+       -50, -40, -30, -30, -30, -30, -40, -50,
+    ],
+    [
+       -20, -10, -10, -10, -10, -10, -10, -20,
+       -10,   0,   0,   0,   0,   0,   0, -10,
+       -10,   0,   5,  10,  10,   5,   0, -10,
+       -10,   5,   5,  10,  10,   5,   5, -10,
+       -10,   0,  10,  10,  10,  10,   0, -10,
+       -10,  10,  10,  10,  10,  10,  10, -10,
+       -10,   5,   0,   0,   0,   0,   5, -10,
+       -20, -10, -10, -10, -10, -10, -10, -20,
+    ],
+    [
+         0,   0,   0,   0,   0,   0,   0,   0,
+         5,  10,  10,  10,  10,  10,  10,   5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+         0,   0,   0,   5,   5,   0,   0,   0,
+    ],
+    [
+       -20, -10, -10,  -5,  -5, -10, -10, -20,
+       -10,   0,   0,   0,   0,   0,   0, -10,
+       -10,   0,   5,   5,   5,   5,   0, -10,
+        -5,   0,   5,   5,   5,   5,   0,  -5,
+         0,   0,   5,   5,   5,   5,   0,  -5,
+       -10,   5,   5,   5,   5,   5,   0, -10,
+       -10,   0,   5,   0,   0,   0,   0, -10,
+       -20, -10, -10,  -5,  -5, -10, -10, -20,
+    ],
+    [
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -20, -30, -30, -40, -40, -30, -30, -20,
+       -10, -20, -20, -20, -20, -20, -20, -10,
+        20,  20,   0,   0,   0,   0,  20,  20,
+        20,  30,  10,   0,   0,  10,  30,  20,
+    ],
+];
+
+/// When to stop searching, search is not limited if nothing is set
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+}
+
+/// Evaluation of position for player to move
+/// * `Centipawns` - material advantage, pawn is 100
+/// * `Mate` - mate in given number of moves, negative if player is mated
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Score {
+    Centipawns(i32),
+    Mate(i32),
+}
+
+/// Result of finished iteration of iterative deepening
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub score: Score,
+    pub nodes: u64,
+    pub time: Duration,
+    /// Principal variation, line both players are expected to play
+    pub pv: Vec<Move>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Copy, Clone)]
+struct Entry {
+    hash: u64,
+    depth: i32,
+    score: i32,
+    bound: Bound,
+    best: Option<Move>,
+}
+
+/// Searcher keeping transposition table between searches, so it should
+/// be reused for all moves of the game
+pub struct Search {
+    table: Vec<Option<Entry>>,
+    stop: Arc<AtomicBool>,
+    stopped: bool,
+    deadline: Option<Instant>,
+    nodes: u64,
+    // quiet moves which caused cutoff, by ply
+    killers: [[Option<Move>; 2]; MAX_PLY],
+}
+
+impl Search {
+    /// Creates searcher with transposition table of given size
+    pub fn new(megabytes: usize) -> Search {
+        let entries = megabytes * 1024 * 1024 / std::mem::size_of::<Entry>();
+        Search {
+            table: vec![None; entries.next_power_of_two().max(1024) / 2],
+            stop: Arc::new(AtomicBool::new(false)),
+            stopped: false,
+            deadline: None,
+            nodes: 0,
+            killers: [[None; 2]; MAX_PLY],
+        }
+    }
+
+    /// Flag which stops search from other thread, best move of the last
+    /// finished iteration is returned. The flag is not cleared by search,
+    /// so owner should clear it before starting a new one
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Forgets everything learned, e.g. for new game
+    pub fn clear(&mut self) {
+        for x in self.table.iter_mut() {
+            *x = None;
+        }
+        self.killers = [[None; 2]; MAX_PLY];
+    }
+
+    /// Finds best move for player to move, reporting every finished
+    /// iteration. At least one iteration is always finished, so the
+    /// move is found unless the game is over
+    pub fn run<F>(
+        &mut self,
+        board: &BoardState,
+        limits: Limits,
+        mut report: F,
+    ) -> Option<Move>
+    where
+        F: FnMut(&SearchInfo),
+    {
+        let start = Instant::now();
+        let mut board = board.clone();
+        self.stopped = false;
+        self.deadline = limits.time.map(|x| start + x);
+        self.nodes = 0;
+        self.killers = [[None; 2]; MAX_PLY];
+
+        let mut best = None;
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        for depth in 1..=max_depth {
+            let score =
+                self.negamax(&mut board, depth as i32, 0, -INFINITY, INFINITY);
+            if self.stopped && depth > 1 {
+                break;
+            }
+            let pv = self.principal_variation(&mut board, depth);
+            best = pv.first().copied();
+            report(&SearchInfo {
+                depth,
+                score: to_score(score),
+                nodes: self.nodes,
+                time: start.elapsed(),
+                pv,
+            });
+            if best.is_none() || score.abs() >= MATE - depth as i32 {
+                break;
+            }
+        }
+        best
+    }
+
+    fn should_stop(&mut self, ply: usize) -> bool {
+        if !self.stopped && self.nodes % CHECK_EVERY == 0 {
+            let timeout = self.deadline.is_some_and(|x| Instant::now() >= x);
+            self.stopped = timeout || self.stop.load(Ordering::Relaxed);
+        }
+        self.stopped || ply >= MAX_PLY - 1
+    }
+
+    fn negamax(
+        &mut self,
+        board: &mut BoardState,
+        depth: i32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+        if ply > 0 {
+            if self.should_stop(ply) {
+                return evaluate(board);
+            }
+            if board.halfmove_clock >= 100
+                || board.repetitions() >= 2
+                || board.is_insufficient_material()
+            {
+                return 0;
+            }
+        }
+
+        let entry = self.probe(board.hash);
+        if let Some(entry) = entry {
+            if ply > 0 && entry.depth >= depth {
+                let score = from_table(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => (),
+                }
+            }
+        }
+
+        let in_check = board.is_check();
+        if depth <= 0 && !in_check {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        let mut moves = board.move_list();
+        if moves.is_empty() {
+            return if in_check { ply as i32 - MATE } else { 0 };
+        }
+        let mut scores = [0; MAX_MOVES];
+        let table_move = entry.and_then(|x| x.best);
+        for (i, x) in moves.iter().enumerate() {
+            scores[i] = self.order(board, *x, table_move, ply);
+        }
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for i in 0..moves.len() {
+            let player_move = next_move(&mut moves, &mut scores, i);
+            let undo = board.make_move(player_move);
+            let score = -self.negamax(board, depth - 1, ply + 1, -beta, -alpha);
+            board.unmake_move(undo);
+            if self.stopped {
+                return best_score.max(score);
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(player_move);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                if !is_capture(board, player_move) {
+                    let killers = &mut self.killers[ply];
+                    if killers[0] != Some(player_move) {
+                        killers[1] = killers[0];
+                        killers[0] = Some(player_move);
+                    }
+                }
+                break;
+            }
+        }
+
+        let bound = if best_score <= original_alpha {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.store(Entry {
+            hash: board.hash,
+            depth,
+            score: to_table(best_score, ply),
+            bound,
+            best: best_move,
+        });
+        best_score
+    }
+
+    /// Searches captures only, until position is quiet
+    fn quiescence(
+        &mut self,
+        board: &mut BoardState,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+        let stand_pat = evaluate(board);
+        if self.should_stop(ply) || stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves = board.move_list();
+        let mut scores = [0; MAX_MOVES];
+        for (i, x) in moves.iter().enumerate() {
+            scores[i] = if is_capture(board, *x)
+                || matches!(x, Move::Promotion(_, _, Promotion::Queen))
+            {
+                capture_order(board, *x)
+            } else {
+                -INFINITY
+            };
+        }
+        for i in 0..moves.len() {
+            let player_move = next_move(&mut moves, &mut scores, i);
+            if scores[i] == -INFINITY {
+                break;
+            }
+            let undo = board.make_move(player_move);
+            let score = -self.quiescence(board, ply + 1, -beta, -alpha);
+            board.unmake_move(undo);
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// Higher is searched earlier: move from the table, captures of most
+    /// valuable victims by least valuable attackers, killers, the rest
+    fn order(
+        &self,
+        board: &BoardState,
+        player_move: Move,
+        table_move: Option<Move>,
+        ply: usize,
+    ) -> i32 {
+        if Some(player_move) == table_move {
+            INFINITY
+        } else if is_capture(board, player_move)
+            || matches!(player_move, Move::Promotion(..))
+        {
+            capture_order(board, player_move)
+        } else if self.killers[ply][0] == Some(player_move) {
+            2
+        } else if self.killers[ply][1] == Some(player_move) {
+            1
+        } else {
+            0
+        }
+    }
+
+    fn probe(&self, hash: u64) -> Option<Entry> {
+        let index = hash as usize & (self.table.len() - 1);
+        self.table[index].filter(|x| x.hash == hash)
+    }
+
+    fn store(&mut self, entry: Entry) {
+        let index = entry.hash as usize & (self.table.len() - 1);
+        let slot = &mut self.table[index];
+        // deeper results of the same position are more valuable
+        if !matches!(slot, Some(x) if x.hash == entry.hash && x.depth > entry.depth)
+        {
+            *slot = Some(entry);
+        }
+    }
+
+    /// Follows best moves from the table, checking they are legal
+    fn principal_variation(
+        &self,
+        board: &mut BoardState,
+        depth: u32,
+    ) -> Vec<Move> {
+        let mut pv = Vec::with_capacity(depth as usize);
+        let mut undos = Vec::with_capacity(depth as usize);
+        while pv.len() < depth as usize {
+            let best = match self.probe(board.hash).and_then(|x| x.best) {
+                Some(x) if board.move_list().contains(&x) => x,
+                _ => break,
+            };
+            pv.push(best);
+            undos.push(board.make_move(best));
+            if board.repetitions() >= 2 {
+                break;
+            }
+        }
+        for undo in undos.into_iter().rev() {
+            board.unmake_move(undo);
+        }
+        pv
+    }
+}
+
+/// Moves best of remaining moves to position `i` and returns it
+fn next_move(moves: &mut MoveList, scores: &mut [i32], i: usize) -> Move {
+    let mut best = i;
+    for j in i + 1..moves.len() {
+        if scores[j] > scores[best] {
+            best = j;
+        }
+    }
+    moves.moves.swap(i, best);
+    scores.swap(i, best);
+    moves[i]
+}
+
+fn is_capture(board: &BoardState, player_move: Move) -> bool {
+    match player_move {
+        Move::Move(from, to) | Move::Promotion(from, to, _) => {
+            board.figure_at(to).is_some()
+                || (Some(to) == board.en_passant
+                    && matches!(board.figure_at(from), Some((PAWN, _))))
+        }
+        _ => false,
+    }
+}
+
+/// Most valuable victim, least valuable attacker
+fn capture_order(board: &BoardState, player_move: Move) -> i32 {
+    let (from, to) = match player_move {
+        Move::Move(from, to) | Move::Promotion(from, to, _) => (from, to),
+        _ => return 0,
+    };
+    let victim = board.figure_at(to).map_or(VALUES[PAWN], |x| VALUES[x.0]);
+    let attacker = board.figure_at(from).map_or(0, |x| x.0 as i32);
+    let promotion = match player_move {
+        Move::Promotion(_, _, x) => VALUES[x.kind()],
+        _ => 0,
+    };
+    1000 + 10 * (victim + promotion) - attacker
+}
+
+/// Static evaluation of position for player to move
+fn evaluate(board: &BoardState) -> i32 {
+    let mut score = 0;
+    for kind in 0..FIGURES.len() {
+        for square in board.pieces(kind, White) {
+            let index = bitboard::index(square);
+            score += VALUES[kind] + SQUARE_BONUS[kind][index ^ 56];
+        }
+        for square in board.pieces(kind, Black) {
+            let index = bitboard::index(square);
+            score -= VALUES[kind] + SQUARE_BONUS[kind][index];
+        }
+    }
+    match board.turn {
+        White => score,
+        Black => -score,
+    }
+}
+
+/// Mate scores are stored relative to the position, not to the root
+fn to_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= MAX_PLY as i32 - MATE {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn from_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= MAX_PLY as i32 - MATE {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+fn to_score(score: i32) -> Score {
+    if score >= MATE - MAX_PLY as i32 {
+        Score::Mate((MATE - score + 1) / 2)
+    } else if score <= MAX_PLY as i32 - MATE {
+        Score::Mate(-(MATE + score) / 2)
+    } else {
+        Score::Centipawns(score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best_move(fen: &str, depth: u32) -> (Option<String>, Score) {
+        let mut board = BoardState::new();
+        board.parse_fen(fen).unwrap();
+        let mut search = Search::new(1);
+        let limits = Limits {
+            depth: Some(depth),
+            time: None,
+        };
+        let mut score = Score::Centipawns(0);
+        let best = search.run(&board, limits, |x| score = x.score);
+        (best.map(|x| x.to_string()), score)
+    }
+
+    #[test]
+    fn test_search() {
+        // back rank mate
+        let (best, score) = best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        assert_eq!(best.as_deref(), Some("a1a8"));
+        assert_eq!(score, Score::Mate(1));
+
+        // free queen
+        let (best, _) = best_move("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2);
+        assert_eq!(best.as_deref(), Some("d2d5"));
+
+        // rook is defended by pawn, so it is not taken by queen
+        let (best, _) = best_move("4k3/4p3/3r4/8/8/8/3Q4/4K3 w - - 0 1", 3);
+        assert_ne!(best.as_deref(), Some("d2d6"));
+
+        // scholar's mate
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+        let (best, score) = best_move(fen, 3);
+        assert_eq!(best.as_deref(), Some("h5f7"));
+        assert_eq!(score, Score::Mate(1));
+
+        let (best, score) = best_move("7k/8/8/8/8/8/8/K7 w - - 0 1", 3);
+        assert!(best.is_some());
+        assert_eq!(score, Score::Centipawns(0));
+    }
+
+    #[test]
+    fn test_search_time() {
+        let board = BoardState::new();
+        let mut search = Search::new(1);
+        let limits = Limits {
+            depth: None,
+            time: Some(Duration::from_millis(100)),
+        };
+        let start = Instant::now();
+        let mut depth = 0;
+        assert!(search.run(&board, limits, |x| depth = x.depth).is_some());
+        assert!(depth >= 1);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,50 @@
+//! Zobrist keys: position hash is xor of random keys of every figure on
+//! its square, castling rights, en passant file and player to move
+
+use super::bitboard::index;
+use super::{Color, Square};
+
+const FIGURE_KEYS: usize = 2 * 6 * 64;
+const CASTLING_KEYS: usize = FIGURE_KEYS;
+const EN_PASSANT_KEYS: usize = CASTLING_KEYS + 4;
+const BLACK_TO_MOVE_KEY: usize = EN_PASSANT_KEYS + 8;
+
+/// Next state and output of SplitMix64 generator
+const fn split_mix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (state, z ^ (z >> 31))
+}
+
+/// Keys are fixed, so hashes can be stored and compared between runs
+const fn keys() -> [u64; BLACK_TO_MOVE_KEY + 1] {
+    let mut result = [0; BLACK_TO_MOVE_KEY + 1];
+    let mut state = 0;
+    let mut i = 0;
+    while i < result.len() {
+        let (next, key) = split_mix(state);
+        state = next;
+        result[i] = key;
+        i += 1;
+    }
+    result
+}
+
+const KEYS: [u64; BLACK_TO_MOVE_KEY + 1] = keys();
+
+pub const BLACK_TO_MOVE: u64 = KEYS[BLACK_TO_MOVE_KEY];
+
+pub fn figure(kind: usize, color: Color, square: Square) -> u64 {
+    KEYS[(color.index() * 6 + kind) * 64 + index(square)]
+}
+
+/// Key of castling right, in `KQkq` order
+pub fn castling(right: usize) -> u64 {
+    KEYS[CASTLING_KEYS + right]
+}
+
+pub fn en_passant(file: u8) -> u64 {
+    KEYS[EN_PASSANT_KEYS + file as usize]
+}
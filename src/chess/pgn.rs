@@ -0,0 +1,522 @@
+//! Portable Game Notation, e.g.
+//! ```text
+//! [Event "Casual game"]
+//! [Site "chessmate"]
+//! [Date "2020.05.01"]
+//! [Round "-"]
+//! [White "1"]
+//! [Black "2"]
+//! [Result "0-1"]
+//!
+//! 1. f3 {[%clk 0:04:58]} e5 2. g4 Qh4# 0-1
+//! ```
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::engine::{BoardState, Color, ParseFenError, ParseSanError};
+
+/// Tags every exported game has, in this order
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+const MAX_LINE: usize = 79;
+
+/// Move in SAN with optional remaining time of player after the move
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnMove {
+    pub san: String,
+    pub clock: Option<Duration>,
+}
+
+/// Record of a single game
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pgn {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<PgnMove>,
+    /// Game termination marker, one of `1-0`, `0-1`, `1/2-1/2`, `*`
+    pub result: String,
+}
+
+/// Reason PGN can't be parsed
+#[derive(Debug, PartialEq)]
+pub enum ParsePgnError {
+    UnterminatedTag,
+    /// Tag pair is not `[Name "Value"]`
+    InvalidTag,
+    UnterminatedComment,
+    UnterminatedVariation,
+    /// Clock comment is not `[%clk h:mm:ss]` or the time is too long
+    InvalidClock,
+}
+
+/// Reason PGN can't be replayed
+#[derive(Debug, PartialEq)]
+pub enum ReplayPgnError {
+    InvalidFen(ParseFenError),
+    /// Move with given index can't be made
+    IllegalMove(usize, ParseSanError),
+}
+
+impl Default for Pgn {
+    fn default() -> Self {
+        Pgn::new()
+    }
+}
+
+impl Pgn {
+    /// Empty game with Seven Tag Roster filled with unknown values
+    pub fn new() -> Pgn {
+        let mut pgn = Pgn {
+            tags: Vec::with_capacity(SEVEN_TAG_ROSTER.len()),
+            moves: Vec::new(),
+            result: "*".to_string(),
+        };
+        for name in SEVEN_TAG_ROSTER.iter() {
+            let value = match *name {
+                "Date" => "????.??.??",
+                "Result" => "*",
+                _ => "?",
+            };
+            pgn.set_tag(name, value);
+        }
+        pgn
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets value of the tag, adding it if there is no such tag yet
+    pub fn set_tag(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.tags.iter_mut().find(|(x, _)| x == name) {
+            Some(tag) => tag.1 = value,
+            None => self.tags.push((name.to_string(), value)),
+        }
+    }
+
+    /// Sets game termination marker and `Result` tag
+    pub fn set_result(&mut self, result: impl Into<String>) {
+        self.result = result.into();
+        let result = self.result.clone();
+        self.set_tag("Result", result);
+    }
+
+    /// Position game starts from, taken from `FEN` tag if there is one
+    pub fn start_position(&self) -> Result<BoardState, ParseFenError> {
+        let mut board = BoardState::new();
+        if let Some(fen) = self.tag("FEN") {
+            board.parse_fen(fen)?;
+        }
+        Ok(board)
+    }
+
+    /// Plays all moves of the game from the starting position
+    /// # Returns
+    /// * `BoardState` - position after the last move
+    /// * `ReplayPgnError` - see enum definition for details
+    pub fn replay(&self) -> Result<BoardState, ReplayPgnError> {
+        let mut board =
+            self.start_position().map_err(ReplayPgnError::InvalidFen)?;
+        for (i, x) in self.moves.iter().enumerate() {
+            let illegal = |err| ReplayPgnError::IllegalMove(i, err);
+            let game_move = board.parse_san(&x.san).map_err(illegal)?;
+            let turn = board.turn();
+            board
+                .validate_move(game_move, turn)
+                .map_err(|_| illegal(ParseSanError::IllegalMove))?;
+        }
+        Ok(board)
+    }
+}
+
+/// Date in PGN format, e.g. `2020.05.01`
+pub fn pgn_date(time: SystemTime) -> String {
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs() / 86400)
+        .unwrap_or(0) as i64;
+    // days since 1970-01-01 to civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+fn write_clock(clock: Duration) -> String {
+    let secs = clock.as_secs();
+    format!(
+        "{{[%clk {}:{:02}:{:02}]}}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Reads `[%clk 1:23:45]` command from comment, fraction of a second
+/// is allowed
+/// # Returns
+/// * `None` - if comment has no clock
+fn parse_clock(comment: &str) -> Result<Option<Duration>, ParsePgnError> {
+    let start = match comment.find("[%clk") {
+        Some(start) => start + "[%clk".len(),
+        None => return Ok(None),
+    };
+    let clock = comment[start..]
+        .find(']')
+        .and_then(|end| parse_clock_time(comment[start..start + end].trim()))
+        .ok_or(ParsePgnError::InvalidClock)?;
+    Ok(Some(clock))
+}
+
+fn parse_clock_time(time: &str) -> Option<Duration> {
+    let mut parts = time.rsplitn(3, ':');
+    let secs = parts.next()?.parse::<f64>().ok()?;
+    let mins = parts.next()?.parse::<u64>().ok()?;
+    let hours = parts.next().map_or(Some(0), |x| x.parse::<u64>().ok())?;
+    let whole = hours
+        .checked_mul(3600)?
+        .checked_add(mins.checked_mul(60)?)?;
+    Duration::from_secs(whole)
+        .checked_add(Duration::try_from_secs_f64(secs).ok()?)
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl fmt::Display for Pgn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for name in SEVEN_TAG_ROSTER.iter() {
+            let value = match *name {
+                "Result" => self.result.as_str(),
+                _ => self.tag(name).unwrap_or("?"),
+            };
+            writeln!(f, "[{} \"{}\"]", name, escape(value))?;
+        }
+        for (name, value) in self.tags.iter() {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) {
+                writeln!(f, "[{} \"{}\"]", name, escape(value))?;
+            }
+        }
+        writeln!(f)?;
+
+        let (mut black_to_move, mut number) = match self.start_position() {
+            Ok(board) => (board.turn() == Color::Black, board.fullmoves()),
+            Err(_) => (false, 1),
+        };
+        let mut tokens = Vec::with_capacity(self.moves.len() * 3 + 1);
+        for (i, x) in self.moves.iter().enumerate() {
+            if !black_to_move {
+                tokens.push(format!("{}.", number));
+            } else if i == 0 {
+                tokens.push(format!("{}...", number));
+            }
+            tokens.push(x.san.clone());
+            if let Some(clock) = x.clock {
+                tokens.push(write_clock(clock));
+            }
+            if black_to_move {
+                number += 1;
+            }
+            black_to_move = !black_to_move;
+        }
+        tokens.push(self.result.clone());
+
+        let mut line = String::with_capacity(MAX_LINE + 1);
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+/// Reads all games from PGN text, e.g. file with many games. Comments
+/// other than clock, variations and annotation glyphs are skipped
+pub fn parse_pgn(text: &str) -> Result<Vec<Pgn>, ParsePgnError> {
+    let mut games = Vec::new();
+    let mut game: Option<Pgn> = None;
+    let mut in_movetext = false;
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            _ if c.is_whitespace() => {}
+            '[' => {
+                if in_movetext {
+                    games.extend(game.take());
+                    in_movetext = false;
+                }
+                let (name, value) = parse_tag(&mut chars)?;
+                let game = game.get_or_insert_with(empty_game);
+                if name == "Result" {
+                    game.result = value.clone();
+                }
+                game.set_tag(&name, value);
+            }
+            '{' => {
+                let end = text[start..]
+                    .find('}')
+                    .ok_or(ParsePgnError::UnterminatedComment)?;
+                let comment = &text[start + 1..start + end];
+                let clock = parse_clock(comment)?;
+                let last = game.as_mut().and_then(|x| x.moves.last_mut());
+                if let (Some(last), Some(clock)) = (last, clock) {
+                    last.clock = Some(clock);
+                }
+                while chars.peek().is_some_and(|(i, _)| *i <= start + end) {
+                    chars.next();
+                }
+            }
+            ';' => {
+                while chars.peek().is_some_and(|(_, x)| *x != '\n') {
+                    chars.next();
+                }
+            }
+            '(' => skip_variation(&mut chars)?,
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, x)) = chars.peek() {
+                    if x.is_whitespace() || "{}();[".contains(*x) {
+                        break;
+                    }
+                    end = i + x.len_utf8();
+                    chars.next();
+                }
+                let token = &text[start..end];
+                if RESULTS.contains(&token) {
+                    // game is finished, next one starts from scratch
+                    let mut finished = game.take().unwrap_or_else(empty_game);
+                    finished.set_result(token);
+                    games.push(finished);
+                    in_movetext = false;
+                    continue;
+                }
+                in_movetext = true;
+                if token.starts_with('$') {
+                    continue;
+                }
+                let game = game.get_or_insert_with(empty_game);
+                let san = token.trim_start_matches(|x: char| {
+                    x.is_ascii_digit() || x == '.'
+                });
+                if !san.is_empty() {
+                    game.moves.push(PgnMove {
+                        san: san.to_string(),
+                        clock: None,
+                    });
+                }
+            }
+        }
+    }
+    games.extend(game);
+    Ok(games)
+}
+
+/// Game without any tags, so only tags present in the text are kept
+fn empty_game() -> Pgn {
+    Pgn {
+        tags: Vec::new(),
+        moves: Vec::new(),
+        result: "*".to_string(),
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::CharIndices<'a>>;
+
+fn parse_tag(chars: &mut Chars) -> Result<(String, String), ParsePgnError> {
+    let mut name = String::new();
+    while let Some((_, x)) = chars.peek() {
+        if x.is_whitespace() || *x == '"' || *x == ']' {
+            break;
+        }
+        name.push(*x);
+        chars.next();
+    }
+    while chars.peek().is_some_and(|(_, x)| x.is_whitespace()) {
+        chars.next();
+    }
+    if name.is_empty() || chars.next().map(|(_, x)| x) != Some('"') {
+        return Err(ParsePgnError::InvalidTag);
+    }
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some((_, '\\')) => match chars.next() {
+                Some((_, x)) => value.push(x),
+                None => return Err(ParsePgnError::UnterminatedTag),
+            },
+            Some((_, '"')) => break,
+            Some((_, x)) => value.push(x),
+            None => return Err(ParsePgnError::UnterminatedTag),
+        }
+    }
+    while chars.peek().is_some_and(|(_, x)| x.is_whitespace()) {
+        chars.next();
+    }
+    match chars.next() {
+        Some((_, ']')) => Ok((name, value)),
+        Some(_) => Err(ParsePgnError::InvalidTag),
+        None => Err(ParsePgnError::UnterminatedTag),
+    }
+}
+
+/// Skips variation, opening parenthesis is already consumed
+fn skip_variation(chars: &mut Chars) -> Result<(), ParsePgnError> {
+    let mut depth = 1;
+    let mut in_comment = false;
+    for (_, x) in chars {
+        match x {
+            '{' => in_comment = true,
+            '}' => in_comment = false,
+            '(' if !in_comment => depth += 1,
+            ')' if !in_comment => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(());
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(ParsePgnError::UnterminatedVariation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOOLS_MATE: &str = r#"[Event "Casual game"]
+[Site "chessmate"]
+[Date "2020.05.01"]
+[Round "-"]
+[White "1"]
+[Black "2"]
+[Result "0-1"]
+[Termination "normal"]
+
+1. f3 {[%clk 0:04:58]} e5 {[%clk 0:04:59]} 2. g4 Qh4# 0-1
+"#;
+
+    #[test]
+    fn test_pgn_round_trip() {
+        let games = parse_pgn(FOOLS_MATE).unwrap();
+        assert_eq!(games.len(), 1);
+        let game = &games[0];
+        assert_eq!(game.result, "0-1");
+        assert_eq!(game.tag("Termination"), Some("normal"));
+        assert_eq!(game.moves.len(), 4);
+        assert_eq!(game.moves[0].clock, Some(Duration::from_secs(298)));
+        assert_eq!(game.moves[3].clock, None);
+        assert_eq!(game.to_string(), FOOLS_MATE);
+        assert!(game.replay().unwrap().legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_parse_multiple_games() {
+        let text = r#"[Event "First"]
+[White "Quote \"me\""]
+
+1. e4 $1 (1. d4 {main (alt)} d5) e5 ; comment
+2. Nf3 Nc6! 1/2-1/2
+
+[Event "Second"]
+[FEN "4k3/8/8/8/8/8/8/R3K3 b - - 0 40"]
+[SetUp "1"]
+
+40... Kd7 41. Ra7+ *
+1. e4 e5 1-0"#;
+        let games = parse_pgn(text).unwrap();
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].tag("White"), Some("Quote \"me\""));
+        let sans: Vec<&str> =
+            games[0].moves.iter().map(|x| x.san.as_str()).collect();
+        assert_eq!(sans, ["e4", "e5", "Nf3", "Nc6!"]);
+        assert_eq!(games[0].result, "1/2-1/2");
+        assert!(games[0].replay().is_ok());
+
+        assert_eq!(games[1].moves.len(), 2);
+        assert_eq!(games[1].result, "*");
+        assert_eq!(
+            games[1].replay().unwrap().export_to_fen(),
+            "8/R2k4/8/8/8/8/8/4K3 b - - 2 41"
+        );
+        assert!(games[1].to_string().contains("\n40... Kd7 41. Ra7+ *\n"));
+        assert_eq!(games[2].tag("Event"), None);
+        assert_eq!(games[2].result, "1-0");
+    }
+
+    #[test]
+    fn test_bad_pgn() {
+        assert_eq!(
+            parse_pgn("[Event \"x"),
+            Err(ParsePgnError::UnterminatedTag)
+        );
+        assert_eq!(parse_pgn("[Event x]"), Err(ParsePgnError::InvalidTag));
+        assert_eq!(
+            parse_pgn("1. e4 {x"),
+            Err(ParsePgnError::UnterminatedComment)
+        );
+        assert_eq!(
+            parse_pgn("1. e4 (1. d4"),
+            Err(ParsePgnError::UnterminatedVariation)
+        );
+        let clocks = ["0:00:1e300", "0:00:-1", "99999999999999999:00:00", "x"];
+        for clock in clocks.iter() {
+            assert_eq!(
+                parse_pgn(&format!("1. e4 {{[%clk {}]}} *", clock)),
+                Err(ParsePgnError::InvalidClock),
+                "{}",
+                clock
+            );
+        }
+        let game = &parse_pgn("1. e4 e5 2. Ke3 *").unwrap()[0];
+        assert!(matches!(
+            game.replay(),
+            Err(ReplayPgnError::IllegalMove(2, ParseSanError::IllegalMove))
+        ));
+    }
+
+    #[test]
+    fn test_pgn_date() {
+        let day = Duration::from_secs(86400);
+        assert_eq!(pgn_date(UNIX_EPOCH), "1970.01.01");
+        assert_eq!(pgn_date(UNIX_EPOCH + day * 18383), "2020.05.01");
+        assert_eq!(pgn_date(UNIX_EPOCH + day * 11016), "2000.02.29");
+    }
+
+    #[test]
+    fn test_line_wrapping() {
+        let mut game = Pgn::new();
+        for _ in 0..10 {
+            for san in ["Nf3", "Nf6", "Ng1", "Ng8"].iter() {
+                game.moves.push(PgnMove {
+                    san: san.to_string(),
+                    clock: Some(Duration::from_secs(3599)),
+                });
+            }
+        }
+        let text = game.to_string();
+        assert!(text.lines().all(|x| x.len() <= MAX_LINE));
+        assert_eq!(parse_pgn(&text).unwrap()[0], game);
+    }
+}
@@ -0,0 +1,503 @@
+//! Client of external engines speaking Universal Chess Interface, they
+//! run as subprocesses and play as bots
+
+use std::fmt::{self, Display};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use actix::Addr;
+
+use super::clock::ClockState;
+use super::engine::{BoardState, Limits, Score, SearchInfo};
+use super::{Bot, ChessAction, ChessGame, ChessWish, Color};
+use crate::bot::{BotPlayer, Engine};
+use crate::domain::Game;
+use crate::runtime::DefaultGameServer;
+
+// how long engine may take to answer commands, other than `go`
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(10);
+
+// how long engine may think over its time budget before it is stopped
+const STOP_GRACE: Duration = Duration::from_secs(1);
+
+/// Reason talking to engine failed
+#[derive(Debug)]
+pub enum UciError {
+    /// Engine can't be started or written to
+    Io(io::Error),
+    /// Engine closed its output
+    Exited,
+    Timeout,
+}
+
+impl Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UciError::Io(err) => write!(f, "engine i/o failed: {}", err),
+            UciError::Exited => write!(f, "engine exited"),
+            UciError::Timeout => write!(f, "engine doesn't answer"),
+        }
+    }
+}
+
+impl From<io::Error> for UciError {
+    fn from(err: io::Error) -> UciError {
+        UciError::Io(err)
+    }
+}
+
+/// External engine to play with
+/// * `name` - name players choose engine by, e.g. `white uci:stockfish`
+/// * `path` - executable of the engine
+/// * `options` - values of engine options set with `setoption`
+/// * `limits` - search limits of every move
+#[derive(Debug, Clone, PartialEq)]
+pub struct UciConfig {
+    pub name: String,
+    pub path: PathBuf,
+    pub options: Vec<(String, String)>,
+    pub limits: Limits,
+}
+
+#[derive(Debug)]
+pub enum ParseUciConfigError {
+    InvalidConfig,
+}
+
+/// Parses `name=path`, engine thinks one second over a move of untimed
+/// game
+impl FromStr for UciConfig {
+    type Err = ParseUciConfigError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(path))
+                if !name.is_empty()
+                    && !path.is_empty()
+                    && !name.contains(char::is_whitespace) =>
+            {
+                Ok(UciConfig {
+                    name: name.to_string(),
+                    path: PathBuf::from(path),
+                    options: Vec::new(),
+                    limits: Limits {
+                        depth: None,
+                        time: Some(Duration::from_secs(1)),
+                    },
+                })
+            }
+            _ => Err(ParseUciConfigError::InvalidConfig),
+        }
+    }
+}
+
+/// Running engine process, it is asked to quit when dropped
+pub struct UciProcess {
+    child: Child,
+    stdin: ChildStdin,
+    // lines of engine output, read by separate thread so reading can
+    // time out
+    lines: Receiver<String>,
+    name: Option<String>,
+}
+
+impl UciProcess {
+    /// Starts engine, sets its options and waits until it is ready
+    pub fn spawn(config: &UciConfig) -> Result<UciProcess, UciError> {
+        let mut child = Command::new(&config.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or(UciError::Exited)?;
+        let stdout = child.stdout.take().ok_or(UciError::Exited)?;
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let sent = line.ok().map(|x| sender.send(x).is_ok());
+                if sent != Some(true) {
+                    break;
+                }
+            }
+        });
+        let mut process = UciProcess {
+            child,
+            stdin,
+            lines,
+            name: None,
+        };
+        process.send("uci")?;
+        let deadline = Instant::now() + ANSWER_TIMEOUT;
+        loop {
+            let line = process.read_line(deadline)?;
+            if line == "uciok" {
+                break;
+            }
+            if let Some(name) = line.strip_prefix("id name ") {
+                process.name = Some(name.to_string());
+            }
+        }
+        for (name, value) in config.options.iter() {
+            process
+                .send(&format!("setoption name {} value {}", name, value))?;
+        }
+        process.wait_ready()?;
+        Ok(process)
+    }
+
+    /// Name engine introduced itself with
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Tells engine that following positions are from another game
+    pub fn new_game(&mut self) -> Result<(), UciError> {
+        self.send("ucinewgame")?;
+        self.wait_ready()
+    }
+
+    /// Searches position after given moves from the initial one
+    /// # Arguments
+    /// * `moves` - moves made so far in UCI notation
+    /// * `limits` - search limits, engine is stopped if it thinks over
+    ///   time limit
+    /// * `clock` - clocks of timed game, engine manages its time by them
+    ///   instead of time limit and is stopped if it runs out of time
+    /// * `report` - called with every search progress engine reports
+    /// # Returns
+    /// * `Some(String)` - best move in UCI notation
+    /// * `None` - if there are no moves in the position
+    pub fn go<F>(
+        &mut self,
+        moves: &[String],
+        limits: Limits,
+        clock: Option<&ClockState>,
+        mut report: F,
+    ) -> Result<Option<String>, UciError>
+    where
+        F: FnMut(&SearchInfo),
+    {
+        let mut board = BoardState::new();
+        let mut position = "position startpos".to_string();
+        if !moves.is_empty() {
+            position.push_str(" moves ");
+            position.push_str(&moves.join(" "));
+        }
+        for x in moves {
+            match board.parse_uci(x) {
+                Ok(x) if board.move_list().contains(&x) => {
+                    board.make_move(x);
+                }
+                _ => break,
+            }
+        }
+        self.send(&position)?;
+        self.send(&go_command(limits, clock))?;
+
+        let time = match clock {
+            Some(clock) => Some(Duration::from_millis(match clock.turn {
+                Color::White => clock.white,
+                Color::Black => clock.black,
+            })),
+            None => limits.time,
+        };
+        let mut stop = time.map(|x| Instant::now() + x + STOP_GRACE);
+        let deadline = stop.map(|x| x + ANSWER_TIMEOUT);
+        loop {
+            let line = match stop {
+                Some(x) => match self.read_line(x) {
+                    Err(UciError::Timeout) => {
+                        self.send("stop")?;
+                        stop = None;
+                        continue;
+                    }
+                    line => line?,
+                },
+                None => match deadline {
+                    Some(x) => self.read_line(x)?,
+                    None => self.lines.recv().map_err(|_| UciError::Exited)?,
+                },
+            };
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("bestmove") => {
+                    let best =
+                        words.next().filter(|x| *x != "(none)" && *x != "0000");
+                    return Ok(best.map(|x| x.to_string()));
+                }
+                Some("info") => {
+                    if let Some(info) = parse_info(&board, &line) {
+                        report(&info);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), UciError> {
+        log::trace!("uci < {}", command);
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    fn read_line(&mut self, deadline: Instant) -> Result<String, UciError> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => {
+                log::trace!("uci > {}", line);
+                Ok(line)
+            }
+            Err(RecvTimeoutError::Timeout) => Err(UciError::Timeout),
+            Err(RecvTimeoutError::Disconnected) => Err(UciError::Exited),
+        }
+    }
+
+    fn wait_ready(&mut self) -> Result<(), UciError> {
+        self.send("isready")?;
+        let deadline = Instant::now() + ANSWER_TIMEOUT;
+        while self.read_line(deadline)? != "readyok" {}
+        Ok(())
+    }
+}
+
+impl Drop for UciProcess {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn go_command(limits: Limits, clock: Option<&ClockState>) -> String {
+    let mut command = "go".to_string();
+    if let Some(depth) = limits.depth {
+        command.push_str(&format!(" depth {}", depth));
+    }
+    match (clock, limits.time) {
+        (Some(clock), _) => command.push_str(&format!(
+            " wtime {} btime {} winc {} binc {}",
+            clock.white, clock.black, clock.increment, clock.increment
+        )),
+        (None, Some(time)) => {
+            command.push_str(&format!(" movetime {}", time.as_millis()))
+        }
+        (None, None) if limits.depth.is_none() => command.push_str(" infinite"),
+        (None, None) => (),
+    }
+    command
+}
+
+/// Reads `info` line with score, ones reporting something else are
+/// skipped. Moves of principal variation are checked on the board, so
+/// it ends on the first illegal one
+pub fn parse_info(board: &BoardState, line: &str) -> Option<SearchInfo> {
+    let mut words = line.split_whitespace();
+    if words.next() != Some("info") {
+        return None;
+    }
+    let mut depth = None;
+    let mut score = None;
+    let mut nodes = 0;
+    let mut time = Duration::from_millis(0);
+    let mut pv = Vec::new();
+    while let Some(word) = words.next() {
+        match word {
+            "depth" => depth = words.next()?.parse().ok(),
+            "nodes" => nodes = words.next()?.parse().ok()?,
+            "time" => time = Duration::from_millis(words.next()?.parse().ok()?),
+            "score" => {
+                score = match (words.next()?, words.next()?.parse().ok()?) {
+                    ("cp", x) => Some(Score::Centipawns(x)),
+                    ("mate", x) => Some(Score::Mate(x)),
+                    _ => None,
+                }
+            }
+            "pv" => {
+                let mut board = board.clone();
+                for x in &mut words {
+                    match board.parse_uci(x) {
+                        Ok(x) if board.move_list().contains(&x) => {
+                            board.make_move(x);
+                            pv.push(x);
+                        }
+                        _ => break,
+                    }
+                }
+            }
+            // the rest of the line is text
+            "string" => break,
+            _ => (),
+        }
+    }
+    Some(SearchInfo {
+        depth: depth?,
+        score: score?,
+        nodes,
+        time,
+        pv,
+    })
+}
+
+/// Engine of single game, the process is started when it is asked to
+/// think for the first time
+pub struct UciEngine {
+    config: Arc<UciConfig>,
+    process: Option<UciProcess>,
+    game: ChessGame,
+    moves: Vec<String>,
+    // clocks of timed game, seen before thinking last time
+    clock: Option<ClockState>,
+}
+
+impl UciEngine {
+    pub fn new(config: Arc<UciConfig>) -> UciEngine {
+        UciEngine {
+            config,
+            process: None,
+            game: ChessGame::with_clock(None),
+            moves: Vec::new(),
+            clock: None,
+        }
+    }
+
+    fn best_move(&mut self) -> Result<Option<String>, UciError> {
+        if self.process.is_none() {
+            let mut process = UciProcess::spawn(&self.config)?;
+            process.new_game()?;
+            self.process = Some(process);
+        }
+        let name = &self.config.name;
+        let process = self.process.as_mut().ok_or(UciError::Exited)?;
+        let limits = self.config.limits;
+        process.go(&self.moves, limits, self.clock.as_ref(), |info| {
+            log::debug!(
+                "{} depth {} score {:?} nodes {}",
+                name,
+                info.depth,
+                info.score,
+                info.nodes
+            )
+        })
+    }
+}
+
+impl Engine<ChessGame> for UciEngine {
+    fn apply(&mut self, action: &ChessAction) {
+        let side = self.game.board.turn();
+        match self.game.apply(side, *action) {
+            Ok((action, _)) => self.moves.push(action.to_string()),
+            Err(err) => log::error!("Engine can't follow {}: {}", action, err),
+        }
+    }
+
+    fn to_move(&self) -> Color {
+        self.game.board.turn()
+    }
+
+    fn think(&mut self) -> Option<ChessAction> {
+        match self.best_move() {
+            Ok(best) => best.and_then(|x| x.parse().ok()),
+            Err(err) => {
+                log::error!("{}: {}", self.config.name, err);
+                // broken process is started again next time
+                self.process = None;
+                None
+            }
+        }
+    }
+
+    fn clock(&mut self, _side: Color, clock: &ClockState) {
+        self.clock = Some(clock.clone());
+    }
+}
+
+/// Checks that engine works and starts its bots waiting for both colors
+/// # Returns
+/// * `String` - name engine introduced itself with
+/// * `UciError` - if engine can't be started
+pub fn start_engine(
+    server: &Addr<DefaultGameServer<ChessGame>>,
+    config: UciConfig,
+) -> Result<String, UciError> {
+    let name = UciProcess::spawn(&config)?
+        .name()
+        .unwrap_or(&config.name)
+        .to_string();
+    let config = Arc::new(config);
+    for color in [Color::White, Color::Black].iter() {
+        let config = config.clone();
+        BotPlayer::spawn(
+            server.clone(),
+            ChessWish::bot(*color, Bot::Uci(config.name.clone())),
+            move || UciEngine::new(config.clone()),
+        );
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_info() {
+        let board = BoardState::new();
+        let info = parse_info(
+            &board,
+            "info depth 3 seldepth 5 score cp -25 nodes 1200 nps 60000 \
+             time 20 pv e2e4 e7e5 e1e2 g1f3",
+        )
+        .unwrap();
+        assert_eq!(info.depth, 3);
+        assert_eq!(info.score, Score::Centipawns(-25));
+        assert_eq!(info.nodes, 1200);
+        assert_eq!(info.time, Duration::from_millis(20));
+        // the last move is illegal after `e1e2`
+        let pv: Vec<_> = info.pv.iter().map(|x| x.to_string()).collect();
+        assert_eq!(pv, ["e2e4", "e7e5", "e1e2"]);
+
+        let info = parse_info(&board, "info depth 9 score mate -2 pv").unwrap();
+        assert_eq!(info.score, Score::Mate(-2));
+        assert!(parse_info(&board, "info string depth 3 score cp 1").is_none());
+        assert!(parse_info(&board, "info currmove e2e4").is_none());
+        assert!(parse_info(&board, "bestmove e2e4").is_none());
+    }
+
+    #[test]
+    fn test_go_command() {
+        let limits = Limits {
+            depth: Some(8),
+            time: Some(Duration::from_secs(1)),
+        };
+        assert_eq!(go_command(limits, None), "go depth 8 movetime 1000");
+        let clock = ClockState {
+            white: 60_000,
+            black: 30_000,
+            turn: Color::Black,
+            increment: 2_000,
+        };
+        assert_eq!(
+            go_command(limits, Some(&clock)),
+            "go depth 8 wtime 60000 btime 30000 winc 2000 binc 2000"
+        );
+        assert_eq!(go_command(Limits::default(), None), "go infinite");
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config: UciConfig = "stockfish=/usr/bin/stockfish".parse().unwrap();
+        assert_eq!(config.name, "stockfish");
+        assert_eq!(config.path, PathBuf::from("/usr/bin/stockfish"));
+        assert!("stockfish".parse::<UciConfig>().is_err());
+        assert!("=/usr/bin/stockfish".parse::<UciConfig>().is_err());
+        assert!("stock fish=/bin".parse::<UciConfig>().is_err());
+    }
+}
@@ -1,11 +1,54 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
 use std::marker::Unpin;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
 
 use crate::core::{GameId, UserId};
+use crate::rating::Rating;
 
 pub trait Wish: FromStr + Debug + Unpin + Clone + Send + 'static {
+    /// Side of the game player plays for, e.g. color in chess
+    type Side: Copy + Debug + PartialEq + Serialize + Unpin + Send + 'static;
+    /// Kind of games players are rated separately in, e.g. blitz in chess
+    type Category: Copy
+        + Eq
+        + Hash
+        + Display
+        + Debug
+        + Serialize
+        + Unpin
+        + Send
+        + 'static;
+
+    /// Key of group of wishes, tickets in the lobby are queued by it
+    type Bucket: Eq + Hash + Debug + Unpin + Send + 'static;
+
     fn is_match(&self, other: &Self) -> bool;
+
+    /// Group this wish belongs to
+    fn bucket(&self) -> Self::Bucket;
+
+    /// Groups all wishes matching this one belong to, the lobby looks for
+    /// opponents only in them
+    fn matching_buckets(&self) -> Vec<Self::Bucket>;
+
+    /// Category of games player wants to play, rating of player in it is
+    /// used to find opponent of similar strength
+    fn category(&self) -> Self::Category;
+
+    /// Sides players of matching wishes get, the first one is of this wish
+    /// # Arguments
+    /// * `other` - wish of the opponent
+    /// * `history` - sides each player had in recent games, the latest
+    ///   last, so they can be balanced
+    fn sides(
+        &self,
+        other: &Self,
+        history: [&[Self::Side]; 2],
+    ) -> [Self::Side; 2];
 }
 
 pub trait Id {
@@ -13,12 +56,148 @@ pub trait Id {
     fn inc(&mut self);
 }
 
-pub trait Game {
-    type Wish: Wish;
+pub trait Game: Unpin + 'static {
+    type Wish: Wish<Side = Self::Side, Category = Self::Category>;
+    /// Side of the game player plays for, e.g. color in chess
+    type Side: Copy + Debug + PartialEq + Serialize + Unpin + Send + 'static;
+    type Action: FromStr + Display + Debug + Clone + Send + 'static;
+    /// Result of finished game, displayed in short form, e.g. `1-0`, and
+    /// serialized with details for clients
+    type Outcome: Display + Debug + Clone + Serialize + Send + 'static;
+    /// Reason why action was rejected, serialized for clients
+    type ActionError: Display + Debug + Serialize + Send + 'static;
+    /// State of players' clocks, serialized for clients
+    type Clock: Debug + Clone + Serialize + Unpin + Send + 'static;
+    /// Kind of games players are rated separately in, e.g. blitz in chess
+    type Category: Copy
+        + Eq
+        + Hash
+        + Display
+        + Debug
+        + Serialize
+        + Unpin
+        + Send
+        + 'static;
+
+    /// Creates game with settings players agreed on in their matching
+    /// wishes, e.g. time control
+    fn new(wishes: [&Self::Wish; 2]) -> Self;
+
+    /// Category the game is rated in
+    fn category(&self) -> Self::Category;
+
+    /// Points player of given side gets for the outcome, 1 for win, 0.5
+    /// for draw and 0 for loss, `None` if the game isn't rated, e.g. it
+    /// was aborted
+    fn score(outcome: &Self::Outcome, side: Self::Side) -> Option<f64>;
+
+    /// Provides user-format representation of game state
+    fn state(&self) -> String;
+
+    /// Performs game action
+    /// # Arguments
+    /// * `side` - side of player who performs an action
+    /// * `action` - action itself
+    /// # Returns
+    /// * `(Action, _)` - action as it was applied, in canonical form, e.g.
+    ///   `Nf3` becomes `g1f3` in chess
+    /// * `(_, Some(Outcome))` - action is correct and ends the game
+    /// * `(_, None)` - action is correct and doesn't end the game
+    /// * `ActionError` - action is incorrect
+    fn apply(
+        &mut self,
+        side: Self::Side,
+        action: Self::Action,
+    ) -> ApplyResult<Self>;
+
+    /// Ends the game with win of opponent of given side
+    fn resign(&mut self, side: Self::Side) -> Self::Outcome;
+
+    /// Ends the game because player of given side left it
+    fn abandon(&mut self, side: Self::Side) -> Self::Outcome;
+
+    /// Ends the game with draw agreed by players
+    fn draw(&mut self) -> Self::Outcome;
+
+    /// Ends the game with draw claimed by player of given side, if rules
+    /// allow it in current position
+    fn claim_draw(
+        &mut self,
+        side: Self::Side,
+    ) -> Result<Self::Outcome, Self::ActionError>;
+
+    /// Current state of clocks, `None` if the game is not timed
+    fn clock(&self) -> Option<Self::Clock>;
+
+    /// Moment player to move runs out of time, if the game is timed
+    fn deadline(&self) -> Option<Instant>;
+
+    /// Ends the game if player to move is out of time
+    fn check_time(&mut self) -> Option<Self::Outcome>;
+
+    /// Record of the game so far in standard format, e.g. PGN for chess
+    fn replay(&self, users: &[(UserId, Self::Side); 2]) -> String;
+}
+
+/// Command player can send to the game in progress
+/// * `Act` - perform game action, e.g. move
+/// * `Resign` - give up the game
+/// * `OfferDraw` - offer draw to opponent
+/// * `AcceptDraw` - accept draw offered by opponent
+/// * `DeclineDraw` - decline draw offered by opponent
+/// * `ClaimDraw` - end the game with draw if rules allow it
+/// * `Leave` - leave the game, counted as abandonment
+#[derive(Debug, Clone)]
+pub enum PlayerCommand<A> {
+    Act(A),
+    Resign,
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    ClaimDraw,
+    Leave,
 }
 
+/// Reason ticket can't be added
+#[derive(Debug)]
 pub enum SetTicketError {
     DuplicateTicket,
+    AlreadyPlaying,
+}
+
+/// Reason ticket can't be cancelled
+#[derive(Debug)]
+pub enum CancelTicketError {
+    NoTicket,
+}
+
+/// Reason ticket left the lobby without a pair
+/// * `Cancelled` - user cancelled it
+/// * `Expired` - it waited for longer than tickets live
+/// * `Disconnected` - user's session is closed
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelReason {
+    Cancelled,
+    Expired,
+    Disconnected,
+}
+
+/// Strength of player waiting for a pair
+/// * `rating` - rating of player in category of the wish
+/// * `range` - how far rating of opponent may be from player's one
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Skill {
+    pub rating: f64,
+    pub range: f64,
+}
+
+/// Pair of users matched by lobby, with sides lobby assigned them
+#[derive(Debug)]
+pub struct Pair<W: Wish> {
+    pub game: GameId,
+    pub users: [(UserId, W); 2],
+    pub sides: [W::Side; 2],
 }
 
 pub trait AbstractLobby<W, O>: Unpin + 'static
@@ -27,19 +206,135 @@ where
     O: PairObserver,
 {
     fn new() -> Self;
+
+    /// Adds ticket of specified user to the lobby, user is paired with
+    /// compatible opponent of the closest rating, the one waiting for the
+    /// longest time of equally close ones
+    /// # Arguments
+    /// * `skill` - strength of user, `None` if user plays anyone, e.g. bot
+    /// # Returns
+    /// * `None` - on success, if no pair was found
+    /// * `Some(Pair)` - on success, if pair was found and game was formed
+    /// * `SetTicketError` - see enum definition for details
     fn add_ticket(
         &mut self,
         user: UserId,
         wish: W,
+        skill: Option<Skill>,
         observer: O,
-    ) -> Result<(), SetTicketError>;
+    ) -> Result<Option<Pair<W>>, SetTicketError>;
+
+    /// Widens rating range of every ticket by `step`, up to `max`, and
+    /// pairs users who fit each other's ranges now
+    fn widen(&mut self, step: f64, max: f64) -> Vec<Pair<W>>;
+
+    /// Removes ticket of specified user from the lobby, its observer is
+    /// told the reason
+    /// # Returns
+    /// * `()` - on success
+    /// * `CancelTicketError` - see enum definition for details
+    fn remove_ticket(
+        &mut self,
+        user: UserId,
+        reason: CancelReason,
+    ) -> Result<(), CancelTicketError>;
+
+    /// Removes tickets which have been waiting for longer than `ttl` by
+    /// the moment `now`, their observers are told they expired
+    /// # Returns
+    /// * `Vec<UserId>` - users whose tickets expired
+    fn expire(&mut self, ttl: Duration, now: Instant) -> Vec<UserId>;
 }
 
-pub trait AbstractGamePool<G> {
+/// Reason command can't be performed
+#[derive(Debug)]
+pub enum DoGameActionError<E> {
+    NotPlaying,
+    NoDrawOffer,
+    /// Action is invalid in current game
+    BadAction(E),
+}
+
+/// Result of `Game::apply`
+pub type ApplyResult<G> = Result<
+    (<G as Game>::Action, Option<<G as Game>::Outcome>),
+    <G as Game>::ActionError,
+>;
+
+/// Game the command was performed in, command as it was applied, see
+/// `Game::apply`, and outcome if the game is over
+pub type ActionResult<G> = Result<
+    (
+        GameId,
+        PlayerCommand<<G as Game>::Action>,
+        Option<<G as Game>::Outcome>,
+    ),
+    DoGameActionError<<G as Game>::ActionError>,
+>;
+
+pub trait AbstractGamePool<G: Game>: Unpin + 'static {
+    fn new() -> Self;
+
+    /// Creates new game for given users with settings of their wishes,
+    /// see `Game::new`
+    fn start_game(
+        &mut self,
+        game: GameId,
+        users: [(UserId, G::Side); 2],
+        wishes: [&G::Wish; 2],
+    );
+
+    /// Returns game user is playing in, if any
+    fn playing(&self, user: UserId) -> Option<GameId>;
+
+    /// Returns users of specified game, if it exists
+    fn users(&self, game: GameId) -> Option<[UserId; 2]>;
+
+    /// Returns state of the game user is playing in, if any
+    fn state(&self, user: UserId) -> Option<(GameId, String)>;
+
+    /// Performs command by specified user in user's current game
+    /// # Returns
+    /// * `(GameId, PlayerCommand, None)` - on success, if command doesn't
+    ///   end the game
+    /// * `(GameId, PlayerCommand, Some(Outcome))` - on success, if command
+    ///   ends the game, game is removed from the pool
+    /// * `DoGameActionError` - see enum definition for details
+    fn do_action(
+        &mut self,
+        user: UserId,
+        command: PlayerCommand<G::Action>,
+    ) -> ActionResult<G>;
+
+    /// Returns state of clocks of the game, if it is timed
+    fn clock(&self, game: GameId) -> Option<G::Clock>;
+
+    /// Returns moment player to move in the game runs out of time
+    fn deadline(&self, game: GameId) -> Option<Instant>;
+
+    /// Ends the game if player to move is out of time, game is removed
+    /// from the pool
+    fn check_time(&mut self, game: GameId) -> Option<G::Outcome>;
+
+    /// Removes game from the pool, returning its users
+    fn end_game(&mut self, game: GameId) -> Option<[UserId; 2]>;
+
+    /// Record of game in progress or finished one, see `Game::replay`,
+    /// pool may keep only the latest finished games
+    fn replay(&self, game: GameId) -> Option<String>;
+
+    /// Rating of user in category, default one for new players
+    fn rating(&self, user: UserId, category: G::Category) -> Rating;
+
+    /// Ratings of user in every category of games user played
+    fn ratings(&self, user: UserId) -> Vec<(G::Category, Rating)>;
 }
 
 pub trait PairObserver: Unpin + 'static {
     fn notify(&self, game: GameId);
+
+    /// Tells that ticket left the lobby without a pair
+    fn cancel(&self, reason: CancelReason);
 }
 
 pub trait PairReactor {
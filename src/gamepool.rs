@@ -0,0 +1,340 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+use crate::bot::BOT_USER_IDS;
+use crate::core::{GameId, UserId};
+use crate::domain::{
+    AbstractGamePool, ActionResult, DoGameActionError, Game, PlayerCommand,
+};
+use crate::rating::{Rating, Ratings};
+
+/// How many finished games are kept for replays, older ones are dropped
+pub const ARCHIVE_SIZE: usize = 1000;
+
+pub struct GameInfo<G: Game> {
+    game: G,
+    users: [(UserId, G::Side); 2],
+    draw_offer: Option<UserId>,
+}
+
+pub struct GamePool<G: Game> {
+    playing_users: HashMap<UserId, GameId>,
+    games: HashMap<GameId, GameInfo<G>>,
+    /// Records of the latest `ARCHIVE_SIZE` finished games
+    archive: HashMap<GameId, String>,
+    /// Finished games in the archive, the oldest first
+    archived: VecDeque<GameId>,
+    ratings: Ratings<G::Category>,
+}
+
+impl<G: Game> GamePool<G> {
+    /// Provides statistic of games currently played by users
+    /// # Returns
+    /// * `(usize,)` - count of games
+    pub fn get_games_stats(&self) -> (usize,) {
+        (self.games.len(),)
+    }
+
+    /// Keeps record of finished game, dropping the oldest one if the
+    /// archive is full
+    fn archive(&mut self, game: GameId, record: String) {
+        if self.archived.len() == ARCHIVE_SIZE {
+            if let Some(oldest) = self.archived.pop_front() {
+                self.archive.remove(&oldest);
+            }
+        }
+        self.archived.push_back(game);
+        self.archive.insert(game, record);
+    }
+
+    /// Updates ratings of players of finished game. Games with bots
+    /// aren't rated, every game bot is a new user
+    fn rate(&mut self, game: GameId, outcome: &G::Outcome) {
+        let info = match self.games.get(&game) {
+            Some(info) => info,
+            None => return,
+        };
+        let [(user1, side1), (user2, _)] = info.users;
+        if user1 >= BOT_USER_IDS || user2 >= BOT_USER_IDS {
+            return;
+        }
+        if let Some(score) = G::score(outcome, side1) {
+            let category = info.game.category();
+            self.ratings.record(category, [user1, user2], score);
+        }
+    }
+}
+
+impl<G: Game> AbstractGamePool<G> for GamePool<G> {
+    fn new() -> GamePool<G> {
+        GamePool {
+            playing_users: HashMap::new(),
+            games: HashMap::new(),
+            archive: HashMap::new(),
+            archived: VecDeque::new(),
+            ratings: Ratings::new(),
+        }
+    }
+
+    fn start_game(
+        &mut self,
+        game: GameId,
+        users: [(UserId, G::Side); 2],
+        wishes: [&G::Wish; 2],
+    ) {
+        log::info!("Start game {} for {:?}", game, users);
+        for (user, _) in &users {
+            self.playing_users.insert(*user, game);
+        }
+        let game_info = GameInfo {
+            game: G::new(wishes),
+            users,
+            draw_offer: None,
+        };
+        self.games.insert(game, game_info);
+    }
+
+    fn playing(&self, user: UserId) -> Option<GameId> {
+        self.playing_users.get(&user).copied()
+    }
+
+    fn users(&self, game: GameId) -> Option<[UserId; 2]> {
+        self.games
+            .get(&game)
+            .map(|info| [info.users[0].0, info.users[1].0])
+    }
+
+    fn state(&self, user: UserId) -> Option<(GameId, String)> {
+        let game_id = self.playing(user)?;
+        self.games
+            .get(&game_id)
+            .map(|info| (game_id, info.game.state()))
+    }
+
+    fn do_action(
+        &mut self,
+        user: UserId,
+        command: PlayerCommand<G::Action>,
+    ) -> ActionResult<G> {
+        let game_id = match self.playing_users.get(&user) {
+            Some(game_id) => *game_id,
+            None => return Err(DoGameActionError::NotPlaying),
+        };
+        let game_info = match self.games.get_mut(&game_id) {
+            Some(game_info) => game_info,
+            None => return Err(DoGameActionError::NotPlaying),
+        };
+        let side = game_info
+            .users
+            .iter()
+            .find(|(user_id, _)| *user_id == user)
+            .map(|(_, side)| *side)
+            .ok_or(DoGameActionError::NotPlaying)?;
+
+        let (command, outcome) = match command {
+            PlayerCommand::Act(action) => {
+                let (action, outcome) = game_info
+                    .game
+                    .apply(side, action)
+                    .map_err(DoGameActionError::BadAction)?;
+                game_info.draw_offer = None;
+                (PlayerCommand::Act(action), outcome)
+            }
+            command @ PlayerCommand::Resign => {
+                (command, Some(game_info.game.resign(side)))
+            }
+            command @ PlayerCommand::Leave => {
+                (command, Some(game_info.game.abandon(side)))
+            }
+            command @ PlayerCommand::OfferDraw => {
+                game_info.draw_offer = Some(user);
+                (command, None)
+            }
+            PlayerCommand::AcceptDraw | PlayerCommand::DeclineDraw
+                if game_info.draw_offer.map_or(true, |x| x == user) =>
+            {
+                return Err(DoGameActionError::NoDrawOffer);
+            }
+            command @ PlayerCommand::AcceptDraw => {
+                (command, Some(game_info.game.draw()))
+            }
+            command @ PlayerCommand::DeclineDraw => {
+                game_info.draw_offer = None;
+                (command, None)
+            }
+            command @ PlayerCommand::ClaimDraw => {
+                let outcome = game_info
+                    .game
+                    .claim_draw(side)
+                    .map_err(DoGameActionError::BadAction)?;
+                (command, Some(outcome))
+            }
+        };
+
+        if let Some(outcome) = &outcome {
+            log::info!("Game {} is over: {}", game_id, outcome);
+            self.rate(game_id, outcome);
+            self.end_game(game_id);
+        }
+        Ok((game_id, command, outcome))
+    }
+
+    fn clock(&self, game: GameId) -> Option<G::Clock> {
+        self.games.get(&game)?.game.clock()
+    }
+
+    fn deadline(&self, game: GameId) -> Option<Instant> {
+        self.games.get(&game)?.game.deadline()
+    }
+
+    fn check_time(&mut self, game: GameId) -> Option<G::Outcome> {
+        let outcome = self.games.get_mut(&game)?.game.check_time()?;
+        log::info!("Game {} is over on time: {}", game, outcome);
+        self.rate(game, &outcome);
+        self.end_game(game);
+        Some(outcome)
+    }
+
+    fn end_game(&mut self, game: GameId) -> Option<[UserId; 2]> {
+        let game_info = self.games.remove(&game)?;
+        let users = [game_info.users[0].0, game_info.users[1].0];
+        for user in &users {
+            self.playing_users.remove(user);
+        }
+        self.archive(game, game_info.game.replay(&game_info.users));
+        Some(users)
+    }
+
+    fn replay(&self, game: GameId) -> Option<String> {
+        match self.games.get(&game) {
+            Some(info) => Some(info.game.replay(&info.users)),
+            None => self.archive.get(&game).cloned(),
+        }
+    }
+
+    fn rating(&self, user: UserId, category: G::Category) -> Rating {
+        self.ratings.get(user, category)
+    }
+
+    fn ratings(&self, user: UserId) -> Vec<(G::Category, Rating)> {
+        self.ratings.of_user(user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::clock::Category;
+    use crate::chess::{
+        ChessAction, ChessGame, Color, DoActionError, InvalidMove, Outcome,
+        Termination,
+    };
+
+    fn act(action: &str) -> PlayerCommand<ChessAction> {
+        PlayerCommand::Act(action.parse().unwrap())
+    }
+
+    #[test]
+    fn test_game_lifecycle() {
+        let mut pool = GamePool::<ChessGame>::new();
+        let wishes = ["white".parse().unwrap(), "black".parse().unwrap()];
+        pool.start_game(
+            0,
+            [(1, Color::White), (2, Color::Black)],
+            [&wishes[0], &wishes[1]],
+        );
+        assert_eq!(pool.playing(1), Some(0));
+        assert_eq!(pool.playing(2), Some(0));
+        assert_eq!(pool.users(0), Some([1, 2]));
+
+        assert!(matches!(
+            pool.do_action(3, act("e2e4")),
+            Err(DoGameActionError::NotPlaying)
+        ));
+        assert!(matches!(
+            pool.do_action(2, act("e7e5")),
+            Err(DoGameActionError::BadAction(DoActionError::InvalidMove(
+                InvalidMove::NotYourTurn
+            )))
+        ));
+        assert!(matches!(pool.do_action(1, act("e2e4")), Ok((0, _, None))));
+        assert!(matches!(
+            pool.do_action(2, PlayerCommand::Resign),
+            Ok((
+                0,
+                _,
+                Some(Outcome {
+                    winner: Some(Color::White),
+                    termination: Termination::Resignation,
+                })
+            ))
+        ));
+
+        assert_eq!(pool.playing(1), None);
+        assert_eq!(pool.playing(2), None);
+        assert_eq!(pool.get_games_stats(), (0,));
+        let replay = pool.replay(0).unwrap();
+        assert!(replay.contains("[White \"1\"]\n[Black \"2\"]\n"));
+        assert!(replay.ends_with("\n1. e4 1-0\n"));
+        assert_eq!(pool.replay(1), None);
+        for game in 1..ARCHIVE_SIZE as GameId {
+            pool.archive(game, String::new());
+        }
+        assert!(pool.replay(0).is_some());
+        pool.archive(ARCHIVE_SIZE as GameId, String::new());
+        assert_eq!(pool.replay(0), None);
+        assert_eq!(pool.archive.len(), ARCHIVE_SIZE);
+
+        let ratings = pool.ratings(1);
+        assert_eq!(ratings.len(), 1);
+        assert_eq!(ratings[0].0, Category::Untimed);
+        assert!(ratings[0].1.rating > pool.ratings(2)[0].1.rating);
+    }
+
+    #[test]
+    fn test_draw_offer() {
+        let mut pool = GamePool::<ChessGame>::new();
+        let wishes = ["white".parse().unwrap(), "black".parse().unwrap()];
+        pool.start_game(
+            0,
+            [(1, Color::White), (2, Color::Black)],
+            [&wishes[0], &wishes[1]],
+        );
+        assert!(matches!(
+            pool.do_action(2, PlayerCommand::AcceptDraw),
+            Err(DoGameActionError::NoDrawOffer)
+        ));
+        assert!(matches!(
+            pool.do_action(1, PlayerCommand::OfferDraw),
+            Ok((0, _, None))
+        ));
+        assert!(matches!(
+            pool.do_action(1, PlayerCommand::AcceptDraw),
+            Err(DoGameActionError::NoDrawOffer)
+        ));
+        assert!(matches!(
+            pool.do_action(2, PlayerCommand::DeclineDraw),
+            Ok((0, _, None))
+        ));
+        assert!(matches!(
+            pool.do_action(2, PlayerCommand::AcceptDraw),
+            Err(DoGameActionError::NoDrawOffer)
+        ));
+        assert!(matches!(
+            pool.do_action(2, PlayerCommand::OfferDraw),
+            Ok((0, _, None))
+        ));
+        assert!(matches!(
+            pool.do_action(1, PlayerCommand::AcceptDraw),
+            Ok((
+                0,
+                _,
+                Some(Outcome {
+                    winner: None,
+                    termination: Termination::Agreement,
+                })
+            ))
+        ));
+        assert_eq!(pool.playing(1), None);
+    }
+}
@@ -2,86 +2,376 @@ use std::fmt::{self, Display};
 use std::marker::PhantomData;
 
 use actix::prelude::StreamHandler;
-use actix::{Actor, Addr, AsyncContext, Handler};
+use actix::{
+    fut, Actor, ActorContext, ActorFuture, Addr, AsyncContext,
+    ContextFutureSpawner, Handler, WrapFuture,
+};
+use actix_web::http::StatusCode;
 use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
 use actix_web_actors::ws;
+use serde::Serialize;
+use serde_json::Value;
 
-use crate::core::UserId;
-use crate::lobby::Lobby;
-use crate::domain::{Game, Wish};
+use crate::bot::BOT_USER_IDS;
+use crate::core::{GameId, UserId};
+use crate::domain::{
+    CancelTicketError, DoGameActionError, Game, PlayerCommand, SetTicketError,
+};
 use crate::observers::{
-    TicketObserver,
-    FindPair,
-    NewGame,
+    CancelTicket, Connect, Disconnect, FindPair, GameEvent, GetRatings,
+    GetReplay, GetState, NewGame, PlayerAction, TicketCancelled,
+};
+use crate::protocol::{
+    ClientFrame, ClientMessage, ErrorCode, ServerFrame, ServerMessage,
 };
-use crate::runtime::GameServer;
+use crate::runtime::DefaultGameServer;
 
-struct WsPlayerSession<W: Wish> {
-    server: Addr<GameServer<W, Lobby<W>>>,
+type RequestId = Option<u64>;
+
+struct WsPlayerSession<G: Game> {
+    server: Addr<DefaultGameServer<G>>,
     user_id: UserId,
-    wish: PhantomData<W>,
+    game: PhantomData<G>,
 }
 
-impl<W: Wish> Handler<NewGame> for WsPlayerSession<W> {
+impl<G: Game> Handler<NewGame> for WsPlayerSession<G> {
     type Result = ();
 
     fn handle(&mut self, msg: NewGame, ctx: &mut ws::WebsocketContext<Self>) {
-        let game_id = msg.0;
-        ctx.text(format!("{}", game_id));
+        let game = msg.0;
+        send(ctx, ServerFrame::push(ServerMessage::NewGame { game }));
+    }
+}
+
+impl<G: Game> Handler<TicketCancelled> for WsPlayerSession<G> {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: TicketCancelled,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        let reason = msg.0;
+        send(
+            ctx,
+            ServerFrame::push(ServerMessage::TicketCancelled { reason }),
+        );
+    }
+}
+
+impl<G: Game> Handler<GameEvent<G>> for WsPlayerSession<G> {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: GameEvent<G>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        let message = match msg {
+            GameEvent::Side(side) => ServerMessage::Side {
+                side: to_value(&side),
+            },
+            GameEvent::Opponent(command) => ServerMessage::Opponent {
+                message: command_message(command),
+            },
+            GameEvent::Clock(clock) => ServerMessage::Clock {
+                state: to_value(&clock),
+            },
+            GameEvent::GameOver(outcome) => ServerMessage::GameOver {
+                outcome: outcome.to_string(),
+                details: to_value(&outcome),
+            },
+        };
+        send(ctx, ServerFrame::push(message));
     }
 }
 
-impl<W: Wish> WsPlayerSession<W> {
-    fn find_pair(&self, wish: &str, ctx: &mut ws::WebsocketContext<Self>) {
-        if let Ok(wish) = wish.parse() {
-            let pair_request = FindPair {
+fn send<A>(ctx: &mut ws::WebsocketContext<A>, frame: ServerFrame)
+where
+    A: Actor<Context = ws::WebsocketContext<A>>,
+{
+    ctx.text(frame.to_json());
+}
+
+/// Protocol representation of command, same as one client sends
+fn command_message<A: Display>(command: PlayerCommand<A>) -> ClientMessage {
+    match command {
+        PlayerCommand::Act(action) => ClientMessage::Move {
+            action: action.to_string(),
+        },
+        PlayerCommand::Resign => ClientMessage::Resign,
+        PlayerCommand::OfferDraw => ClientMessage::OfferDraw,
+        PlayerCommand::AcceptDraw => ClientMessage::AcceptDraw,
+        PlayerCommand::DeclineDraw => ClientMessage::DeclineDraw,
+        PlayerCommand::ClaimDraw => ClientMessage::ClaimDraw,
+        PlayerCommand::Leave => ClientMessage::Leave,
+    }
+}
+
+fn ticket_error_frame(id: RequestId, err: SetTicketError) -> ServerFrame {
+    let code = match err {
+        SetTicketError::DuplicateTicket => ErrorCode::DuplicateTicket,
+        SetTicketError::AlreadyPlaying => ErrorCode::AlreadyPlaying,
+    };
+    ServerFrame::error(id, code, format!("{:?}", err))
+}
+
+fn action_error_frame<E: Display + Serialize>(
+    id: RequestId,
+    err: DoGameActionError<E>,
+) -> ServerFrame {
+    let message = match err {
+        DoGameActionError::NotPlaying => ServerMessage::Error {
+            code: ErrorCode::NotPlaying,
+            message: "you are not playing".to_string(),
+            reason: None,
+        },
+        DoGameActionError::NoDrawOffer => ServerMessage::Error {
+            code: ErrorCode::NoDrawOffer,
+            message: "there is no draw offer to answer".to_string(),
+            reason: None,
+        },
+        DoGameActionError::BadAction(err) => ServerMessage::Error {
+            code: ErrorCode::BadAction,
+            message: err.to_string(),
+            reason: Some(to_value(&err)),
+        },
+    };
+    ServerFrame::reply(id, message)
+}
+
+/// Game types are plain data, so serialization can't fail
+fn to_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+impl<G: Game> WsPlayerSession<G> {
+    fn find_pair(
+        &self,
+        id: RequestId,
+        wish: &str,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        let wish = match wish.parse() {
+            Ok(wish) => wish,
+            Err(_) => {
+                let msg = format!("invalid wish: {}", wish);
+                return send(
+                    ctx,
+                    ServerFrame::error(id, ErrorCode::InvalidWish, msg),
+                );
+            }
+        };
+        let pair_request = FindPair {
+            user_id: self.user_id,
+            wish,
+            addr: ctx.address().recipient(),
+            cancel_addr: ctx.address().recipient(),
+        };
+        self.server
+            .send(pair_request)
+            .into_actor(self)
+            .then(move |res, _, ctx| {
+                match res {
+                    Ok(Ok(())) => {
+                        send(ctx, ServerFrame::reply(id, ServerMessage::Ok))
+                    }
+                    Ok(Err(err)) => send(ctx, ticket_error_frame(id, err)),
+                    Err(_) => ctx.stop(),
+                }
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    fn cancel(&self, id: RequestId, ctx: &mut ws::WebsocketContext<Self>) {
+        self.server
+            .send(CancelTicket {
+                user_id: self.user_id,
+            })
+            .into_actor(self)
+            .then(move |res, _, ctx| {
+                match res {
+                    Ok(Ok(())) => {
+                        send(ctx, ServerFrame::reply(id, ServerMessage::Ok))
+                    }
+                    Ok(Err(CancelTicketError::NoTicket)) => send(
+                        ctx,
+                        ServerFrame::error(
+                            id,
+                            ErrorCode::NoTicket,
+                            "you are not looking for a pair",
+                        ),
+                    ),
+                    Err(_) => ctx.stop(),
+                }
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    fn command(
+        &self,
+        id: RequestId,
+        command: PlayerCommand<G::Action>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        let action = PlayerAction {
+            user_id: self.user_id,
+            command,
+        };
+        self.server
+            .send(action)
+            .into_actor(self)
+            .then(move |res, _, ctx| {
+                match res {
+                    Ok(Ok(_)) => {
+                        send(ctx, ServerFrame::reply(id, ServerMessage::Ok))
+                    }
+                    Ok(Err(err)) => send(ctx, action_error_frame(id, err)),
+                    Err(_) => ctx.stop(),
+                }
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    fn make_move(
+        &self,
+        id: RequestId,
+        action: &str,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        match action.parse() {
+            Ok(action) => self.command(id, PlayerCommand::Act(action), ctx),
+            Err(_) => {
+                let msg = format!("invalid action: {}", action);
+                send(ctx, ServerFrame::error(id, ErrorCode::InvalidAction, msg))
+            }
+        }
+    }
+
+    fn position(&self, id: RequestId, ctx: &mut ws::WebsocketContext<Self>) {
+        self.server
+            .send(GetState {
                 user_id: self.user_id,
-                wish,
-                addr: ctx.address().recipient(),
-            };
-            self.server.do_send(pair_request);
+            })
+            .into_actor(self)
+            .then(move |res, _, ctx| {
+                match res {
+                    Ok(Some((game, state))) => send(
+                        ctx,
+                        ServerFrame::reply(
+                            id,
+                            ServerMessage::Position { game, state },
+                        ),
+                    ),
+                    Ok(None) => send(
+                        ctx,
+                        action_error_frame::<G::ActionError>(
+                            id,
+                            DoGameActionError::NotPlaying,
+                        ),
+                    ),
+                    Err(_) => ctx.stop(),
+                }
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    fn handle_frame(
+        &self,
+        frame: ClientFrame,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        let id = frame.id;
+        match frame.message {
+            ClientMessage::Find { wish } => self.find_pair(id, &wish, ctx),
+            ClientMessage::Cancel => self.cancel(id, ctx),
+            ClientMessage::Move { action } => self.make_move(id, &action, ctx),
+            ClientMessage::Resign => {
+                self.command(id, PlayerCommand::Resign, ctx)
+            }
+            ClientMessage::OfferDraw => {
+                self.command(id, PlayerCommand::OfferDraw, ctx)
+            }
+            ClientMessage::AcceptDraw => {
+                self.command(id, PlayerCommand::AcceptDraw, ctx)
+            }
+            ClientMessage::DeclineDraw => {
+                self.command(id, PlayerCommand::DeclineDraw, ctx)
+            }
+            ClientMessage::ClaimDraw => {
+                self.command(id, PlayerCommand::ClaimDraw, ctx)
+            }
+            ClientMessage::Position => self.position(id, ctx),
+            ClientMessage::Leave => self.command(id, PlayerCommand::Leave, ctx),
         }
     }
 }
 
-impl<W: Wish> Actor for WsPlayerSession<W> {
+impl<G: Game> Actor for WsPlayerSession<G> {
     type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.server.do_send(Connect {
+            user_id: self.user_id,
+            addr: ctx.address().recipient(),
+        });
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        self.server.do_send(Disconnect {
+            user_id: self.user_id,
+            addr: ctx.address().recipient(),
+        });
+    }
 }
 
-impl<W: Wish> StreamHandler<Result<ws::Message, ws::ProtocolError>>
-    for WsPlayerSession<W>
+impl<G: Game> StreamHandler<Result<ws::Message, ws::ProtocolError>>
+    for WsPlayerSession<G>
 {
     fn handle(
         &mut self,
         msg: Result<ws::Message, ws::ProtocolError>,
         ctx: &mut Self::Context,
     ) {
-
         log::info!("websocket Message: {:?}", msg);
 
-        if let Ok(message) = msg {
-            match message {
-                ws::Message::Text(txt) => {
-                    let mut args = txt.splitn(2, "?"); 
-                    let cmd = args.next().unwrap();
-                    let attrs = args.next().unwrap(); 
-                    match cmd {
-                        "/find" => self.find_pair(attrs, ctx),
-                        //TODO: implement playing game
-                        _ => ctx.text("Henlo"),
-                    }
-                },
-                _ => ctx.text("What are you doing"),
+        match msg {
+            Ok(ws::Message::Text(txt)) => match ClientFrame::parse(&txt) {
+                Ok(frame) => self.handle_frame(frame, ctx),
+                Err(err) => send(ctx, err.to_frame()),
+            },
+            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
+            Ok(ws::Message::Pong(_)) => (),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Ok(_) => send(
+                ctx,
+                ServerFrame::error(
+                    None,
+                    ErrorCode::BadFrame,
+                    "only text frames are supported",
+                ),
+            ),
+            Err(err) => {
+                log::warn!("websocket protocol error: {:?}", err);
+                ctx.stop();
             }
-        } else {
-            unimplemented!();
         }
     }
 }
 
+/// Errors of starting a session, either websocket handshake fails or
+/// user id belongs to bots
 #[derive(Debug)]
 pub enum ReqError {
-    InvalidWish,
+    InvalidHandshake,
+    ReservedUserId,
 }
 
 impl Display for ReqError {
@@ -90,22 +380,71 @@ impl Display for ReqError {
     }
 }
 
-impl ResponseError for ReqError {}
+impl ResponseError for ReqError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ReqError::InvalidHandshake => StatusCode::BAD_REQUEST,
+            ReqError::ReservedUserId => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+/// Record of the game in standard format, e.g. PGN for chess
+pub async fn replay<G: Game>(
+    info: web::Path<GameId>,
+    server: web::Data<Addr<DefaultGameServer<G>>>,
+) -> HttpResponse {
+    let game = info.into_inner();
+    match server.send(GetReplay { game }).await {
+        Ok(Some(replay)) => HttpResponse::Ok()
+            .content_type("text/plain; charset=utf-8")
+            .body(replay),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// Ratings of user in every category of games user played, e.g.
+/// `{"blitz": {"rating": 1620.5, "deviation": 80.2, "volatility": 0.06}}`
+pub async fn ratings<G: Game>(
+    info: web::Path<UserId>,
+    server: web::Data<Addr<DefaultGameServer<G>>>,
+) -> HttpResponse {
+    let request = GetRatings::<G::Category> {
+        user_id: info.into_inner(),
+        category: PhantomData,
+    };
+    match server.send(request).await {
+        Ok(ratings) => {
+            let ratings: serde_json::Map<String, Value> = ratings
+                .iter()
+                .map(|(category, rating)| {
+                    (category.to_string(), to_value(rating))
+                })
+                .collect();
+            HttpResponse::Ok().json(ratings)
+        }
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
 
 pub async fn new_session<G: Game>(
     req: HttpRequest,
     stream: web::Payload,
     info: web::Path<UserId>,
-    server: web::Data<Addr<GameServer<G::Wish, Lobby<G::Wish>>>>,
+    server: web::Data<Addr<DefaultGameServer<G>>>,
 ) -> Result<HttpResponse, ReqError> {
     log::info!("request: {:?}", info);
 
     let user_id = info.into_inner();
+    if user_id >= BOT_USER_IDS {
+        return Err(ReqError::ReservedUserId);
+    }
     //TODO: fuck this error handling, aaaah
     let session = WsPlayerSession {
         server: server.get_ref().clone(),
         user_id,
-        wish: PhantomData,
+        game: PhantomData,
     };
-    ws::start(session, &req, stream).map_err(|_| ReqError::InvalidWish)
+    ws::start(session, &req, stream).map_err(|_| ReqError::InvalidHandshake)
 }
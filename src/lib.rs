@@ -0,0 +1,12 @@
+pub mod bot;
+pub mod chess;
+pub mod core;
+pub mod domain;
+pub mod gamepool;
+pub mod handlers;
+pub mod lobby;
+pub mod observers;
+pub mod protocol;
+pub mod rating;
+pub mod runtime;
+pub mod server;
@@ -1,26 +1,201 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
 
+use crate::bot::BOT_USER_IDS;
 use crate::core::{GameId, UserId};
-use crate::domain::{AbstractLobby, Id, PairObserver, SetTicketError, Wish};
+use crate::domain::{
+    AbstractLobby, CancelReason, CancelTicketError, Id, Pair, PairObserver,
+    SetTicketError, Skill, Wish,
+};
 use crate::observers::TicketObserver;
 
-pub struct Lobby<W: Wish> {
-    tickets: HashMap<UserId, (W, TicketObserver)>,
+// games remembered for every user to balance sides
+const HISTORY_LENGTH: usize = 10;
+// width of rating bands tickets are queued in, it's narrower than the
+// narrowest range, so players of one band always fit each other
+const BAND_WIDTH: f64 = 50.0;
+
+/// Rating band of user, `None` for users without skill
+type Band = Option<i64>;
+
+fn band(rating: f64) -> Band {
+    Some((rating / BAND_WIDTH).floor() as i64)
+}
+
+/// Ticket of user waiting for a pair
+struct Ticket<W: Wish, O: PairObserver> {
+    wish: W,
+    skill: Option<Skill>,
+    observer: O,
+    /// Number of ticket in order of arrival
+    number: u64,
+    since: Instant,
+}
+
+pub struct Lobby<W: Wish, O: PairObserver = TicketObserver> {
+    tickets: HashMap<UserId, Ticket<W, O>>,
+    /// Users waiting with wishes of every bucket, by rating bands and
+    /// numbers of tickets
+    queues: HashMap<W::Bucket, BTreeMap<Band, BTreeMap<u64, UserId>>>,
+    next_ticket: u64,
     game_counter: GameId,
+    /// Sides users had in recent games, the latest last
+    history: HashMap<UserId, Vec<W::Side>>,
 }
 
-impl<W: Wish> Lobby<W> {
+/// Distance between ratings of users, `None` if they don't fit each
+/// other's ranges, users without skill play anyone
+fn distance(first: Option<Skill>, second: Option<Skill>) -> Option<f64> {
+    match (first, second) {
+        (Some(first), Some(second)) => {
+            let distance = (first.rating - second.rating).abs();
+            if distance <= first.range && distance <= second.range {
+                Some(distance)
+            } else {
+                None
+            }
+        }
+        _ => Some(0.0),
+    }
+}
+
+impl<W: Wish, O: PairObserver> Lobby<W, O> {
+    /// Compatible user of the closest rating band, who has been waiting
+    /// for the longest time among the ones of equally close bands. Only
+    /// bands within rating range in matching buckets are looked through,
+    /// each of them up to the first ticket in range, which is the first
+    /// ticket of the same band, so it takes O(b) for b bands in range
+    /// unless ranges of neighbouring bands are narrower than distances
+    fn best_match(
+        &self,
+        user: UserId,
+        wish: &W,
+        skill: Option<Skill>,
+    ) -> Option<UserId> {
+        let own = skill.and_then(|x| band(x.rating));
+        let (low, high) = match skill {
+            Some(skill) => (
+                band(skill.rating - skill.range),
+                band(skill.rating + skill.range),
+            ),
+            None => (Some(i64::MIN), Some(i64::MAX)),
+        };
+        let mut best = None;
+        for bucket in wish.matching_buckets() {
+            let bands = match self.queues.get(&bucket) {
+                Some(bands) => bands,
+                None => continue,
+            };
+            let near = bands.range(None..=None).chain(bands.range(low..=high));
+            for (other, queue) in near {
+                let found = queue.iter().find(|(_, x)| {
+                    let ticket = &self.tickets[*x];
+                    **x != user
+                        && ticket.wish.is_match(wish)
+                        && distance(ticket.skill, skill).is_some()
+                });
+                if let Some((number, x)) = found {
+                    let bands_apart = match (own, *other) {
+                        (Some(own), Some(other)) => (own - other).abs(),
+                        _ => 0,
+                    };
+                    let candidate = (bands_apart, *number, *x);
+                    best = Some(best.map_or(candidate, |x| candidate.min(x)));
+                }
+            }
+        }
+        best.map(|(_, _, x)| x)
+    }
+
+    fn insert(&mut self, user: UserId, ticket: Ticket<W, O>) {
+        self.queues
+            .entry(ticket.wish.bucket())
+            .or_default()
+            .entry(ticket.skill.and_then(|x| band(x.rating)))
+            .or_default()
+            .insert(ticket.number, user);
+        self.tickets.insert(user, ticket);
+    }
 
+    fn remove(&mut self, user: UserId) -> Option<Ticket<W, O>> {
+        let ticket = self.tickets.remove(&user)?;
+        let bucket = ticket.wish.bucket();
+        if let Some(bands) = self.queues.get_mut(&bucket) {
+            let band = ticket.skill.and_then(|x| band(x.rating));
+            if let Some(queue) = bands.get_mut(&band) {
+                queue.remove(&ticket.number);
+                if queue.is_empty() {
+                    bands.remove(&band);
+                }
+            }
+            if bands.is_empty() {
+                self.queues.remove(&bucket);
+            }
+        }
+        Some(ticket)
+    }
+
+    /// Forms pair of waiting user and given one, notifying both of them
+    fn pair(
+        &mut self,
+        waiting: UserId,
+        user: UserId,
+        wish: W,
+        observer: &O,
+    ) -> Pair<W> {
+        let ticket = self.remove(waiting).expect("paired user has a ticket");
+        log::info!("Find pair for {} and {}", waiting, user);
+        ticket.observer.notify(self.game_counter);
+        observer.notify(self.game_counter);
+        let sides = self.assign_sides([(waiting, &ticket.wish), (user, &wish)]);
+        let pair = Pair {
+            game: self.game_counter,
+            users: [(waiting, ticket.wish), (user, wish)],
+            sides,
+        };
+        self.game_counter.inc();
+        pair
+    }
+
+    /// Assigns sides to matched users, honouring their wishes and
+    /// balancing sides they had before
+    fn assign_sides(&mut self, users: [(UserId, &W); 2]) -> [W::Side; 2] {
+        let [(user1, wish1), (user2, wish2)] = users;
+        let empty = Vec::new();
+        let sides = wish1.sides(
+            wish2,
+            [
+                self.history.get(&user1).unwrap_or(&empty),
+                self.history.get(&user2).unwrap_or(&empty),
+            ],
+        );
+        for (user, side) in [(user1, sides[0]), (user2, sides[1])].iter() {
+            // bots get new ids for every game, their history would pile up
+            if *user >= BOT_USER_IDS {
+                continue;
+            }
+            let history = self.history.entry(*user).or_default();
+            if history.len() == HISTORY_LENGTH {
+                history.remove(0);
+            }
+            history.push(*side);
+        }
+        sides
+    }
 }
 
-impl<W> AbstractLobby<W, TicketObserver> for Lobby<W>
+impl<W, O> AbstractLobby<W, O> for Lobby<W, O>
 where
     W: Wish,
+    O: PairObserver,
 {
-    fn new() -> Lobby<W> {
+    fn new() -> Lobby<W, O> {
         Lobby {
             tickets: HashMap::new(),
+            queues: HashMap::new(),
+            next_ticket: 0,
             game_counter: GameId::new(),
+            history: HashMap::new(),
         }
     }
 
@@ -28,33 +203,211 @@ where
         &mut self,
         user: UserId,
         new_wish: W,
-        new_observer: TicketObserver,
-    ) -> Result<(), SetTicketError> {
-        log::debug!("Got wish {:?} from {:?}", new_wish, user);
-        let mut paired = false;
-        let mut paired_user = Option::None;
-        for ticket in &self.tickets {
-            let (wish, observer) = ticket.1;
-            let user_id = *ticket.0;
-
-            if wish.is_match(&new_wish) {
-                paired = true;
-                paired_user.replace(user_id);
-                log::info!("Find pair for {} and {}", user_id, user);
-                observer.notify(self.game_counter);
-                new_observer.notify(self.game_counter);
-                self.game_counter.inc();
-                break;
-            }
-        }
-        if paired {
-            let _ = self.tickets.remove(&paired_user.unwrap());
-            Ok(())
-        } else {
-            match self.tickets.insert(user, (new_wish, new_observer)) {
-                None => Ok(()),
-                Some(_) => Err(SetTicketError::DuplicateTicket),
+        skill: Option<Skill>,
+        new_observer: O,
+    ) -> Result<Option<Pair<W>>, SetTicketError> {
+        log::debug!("Got wish {:?} from {:?} {:?}", new_wish, user, skill);
+        if self.tickets.contains_key(&user) {
+            return Err(SetTicketError::DuplicateTicket);
+        }
+        match self.best_match(user, &new_wish, skill) {
+            Some(paired_user) => {
+                Ok(Some(self.pair(paired_user, user, new_wish, &new_observer)))
+            }
+            None => {
+                let ticket = Ticket {
+                    wish: new_wish,
+                    skill,
+                    observer: new_observer,
+                    number: self.next_ticket,
+                    since: Instant::now(),
+                };
+                self.next_ticket += 1;
+                self.insert(user, ticket);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Only users whose ranges grew look for a pair again, the rest have
+    /// been checked against everyone already, so it takes O(m * b) for
+    /// m widened tickets and b bands in their ranges, and O(n) for n
+    /// tickets once every range reaches `max`
+    fn widen(&mut self, step: f64, max: f64) -> Vec<Pair<W>> {
+        let mut waiting = Vec::new();
+        for (user, ticket) in self.tickets.iter_mut() {
+            if let Some(skill) = ticket.skill.as_mut() {
+                if skill.range < max {
+                    skill.range = (skill.range + step).min(max);
+                    waiting.push((ticket.number, *user));
+                }
+            }
+        }
+        waiting.sort();
+        let mut pairs = Vec::new();
+        for (_, user) in waiting {
+            let paired_user = match self.tickets.get(&user) {
+                Some(ticket) => {
+                    self.best_match(user, &ticket.wish, ticket.skill)
+                }
+                // paired already
+                None => continue,
+            };
+            if let Some(paired_user) = paired_user {
+                let ticket = self.remove(user).unwrap();
+                pairs.push(self.pair(
+                    paired_user,
+                    user,
+                    ticket.wish,
+                    &ticket.observer,
+                ));
             }
         }
+        pairs
+    }
+
+    fn remove_ticket(
+        &mut self,
+        user: UserId,
+        reason: CancelReason,
+    ) -> Result<(), CancelTicketError> {
+        let ticket = self.remove(user).ok_or(CancelTicketError::NoTicket)?;
+        log::info!("Ticket of {} is removed: {:?}", user, reason);
+        ticket.observer.cancel(reason);
+        Ok(())
+    }
+
+    fn expire(&mut self, ttl: Duration, now: Instant) -> Vec<UserId> {
+        let expired: Vec<_> = self
+            .tickets
+            .iter()
+            .filter(|(_, ticket)| {
+                now.saturating_duration_since(ticket.since) > ttl
+            })
+            .map(|(user, _)| *user)
+            .collect();
+        for user in expired.iter() {
+            let _ = self.remove_ticket(*user, CancelReason::Expired);
+        }
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::ChessWish;
+
+    #[derive(Debug)]
+    struct Observer;
+
+    impl PairObserver for Observer {
+        fn notify(&self, _: GameId) {}
+
+        fn cancel(&self, _: CancelReason) {}
+    }
+
+    fn skill(rating: f64, range: f64) -> Option<Skill> {
+        Some(Skill { rating, range })
+    }
+
+    fn add(
+        lobby: &mut Lobby<ChessWish, Observer>,
+        user: UserId,
+        wish: &str,
+        skill: Option<Skill>,
+    ) -> Option<[UserId; 2]> {
+        lobby
+            .add_ticket(user, wish.parse().unwrap(), skill, Observer)
+            .unwrap()
+            .map(|pair| [pair.users[0].0, pair.users[1].0])
+    }
+
+    #[test]
+    fn test_closest_rating() {
+        let mut lobby = Lobby::new();
+        assert_eq!(add(&mut lobby, 1, "white", skill(1500.0, 300.0)), None);
+        assert_eq!(add(&mut lobby, 2, "white", skill(1700.0, 300.0)), None);
+        assert_eq!(add(&mut lobby, 3, "white", skill(2200.0, 300.0)), None);
+        assert_eq!(
+            add(&mut lobby, 4, "black", skill(1650.0, 300.0)),
+            Some([2, 4])
+        );
+        // the ones left are too weak or too strong
+        assert_eq!(add(&mut lobby, 5, "black", skill(1800.0, 100.0)), None);
+        assert_eq!(add(&mut lobby, 6, "black", None), Some([1, 6]));
+        assert!(matches!(
+            lobby.add_ticket(5, "white".parse().unwrap(), None, Observer),
+            Err(SetTicketError::DuplicateTicket)
+        ));
+    }
+
+    #[test]
+    fn test_longest_waiting() {
+        let mut lobby = Lobby::new();
+        assert_eq!(add(&mut lobby, 1, "white", skill(1510.0, 300.0)), None);
+        assert_eq!(add(&mut lobby, 2, "white", skill(1530.0, 300.0)), None);
+        assert_eq!(add(&mut lobby, 3, "white", skill(1610.0, 300.0)), None);
+        assert_eq!(add(&mut lobby, 4, "white 3+2", None), None);
+        // the first two are of the same band
+        assert_eq!(
+            add(&mut lobby, 5, "black", skill(1535.0, 300.0)),
+            Some([1, 5])
+        );
+        // bands of the ones left are equally close
+        assert_eq!(
+            add(&mut lobby, 6, "black", skill(1575.0, 300.0)),
+            Some([2, 6])
+        );
+        assert_eq!(add(&mut lobby, 7, "black", None), Some([3, 7]));
+        assert_eq!(add(&mut lobby, 8, "black", None), None);
+        assert_eq!(add(&mut lobby, 9, "any 3+2", None), Some([4, 9]));
+        // bots have no history kept
+        let bot = BOT_USER_IDS + 1;
+        assert_eq!(add(&mut lobby, bot, "white", None), Some([8, bot]));
+        assert!(lobby.history.contains_key(&8));
+        assert!(!lobby.history.contains_key(&bot));
+        assert!(lobby.tickets.is_empty() && lobby.queues.is_empty());
+    }
+
+    #[test]
+    fn test_widen() {
+        let mut lobby = Lobby::new();
+        assert_eq!(add(&mut lobby, 1, "white", skill(1500.0, 100.0)), None);
+        assert_eq!(add(&mut lobby, 2, "black", skill(1750.0, 200.0)), None);
+        assert_eq!(add(&mut lobby, 3, "black 3+2", skill(1500.0, 100.0)), None);
+        assert!(lobby.widen(100.0, 1000.0).is_empty());
+        let pairs = lobby.widen(100.0, 1000.0);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!([pairs[0].users[0].0, pairs[0].users[1].0], [2, 1]);
+        assert!(lobby.widen(1000.0, 1000.0).is_empty());
+
+        // ranges stop growing at the limit
+        assert_eq!(add(&mut lobby, 4, "white", skill(1000.0, 100.0)), None);
+        assert_eq!(add(&mut lobby, 5, "black", skill(2000.0, 900.0)), None);
+        for _ in 0..10 {
+            assert!(lobby.widen(100.0, 300.0).is_empty());
+        }
+        let range = |user| lobby.tickets[&user].skill.unwrap().range;
+        assert_eq!((range(4), range(5)), (300.0, 900.0));
+    }
+
+    #[test]
+    fn test_remove_ticket() {
+        let mut lobby = Lobby::new();
+        assert_eq!(add(&mut lobby, 1, "white", None), None);
+        assert_eq!(add(&mut lobby, 2, "white 3+2", None), None);
+        assert!(matches!(
+            lobby.remove_ticket(3, CancelReason::Cancelled),
+            Err(CancelTicketError::NoTicket)
+        ));
+        assert!(lobby.remove_ticket(1, CancelReason::Cancelled).is_ok());
+        assert_eq!(add(&mut lobby, 3, "black", None), None);
+        let ttl = Duration::from_secs(60);
+        assert!(lobby.expire(ttl, Instant::now()).is_empty());
+        assert_eq!(add(&mut lobby, 4, "black 3+2", None), Some([2, 4]));
+        let later = Instant::now() + ttl * 2;
+        assert_eq!(lobby.expire(ttl, later), [3]);
+        assert!(lobby.tickets.is_empty() && lobby.queues.is_empty());
     }
 }
@@ -1,20 +1,47 @@
-mod core;
-mod domain;
-mod handlers;
-mod lobby;
-mod observers;
-mod runtime;
-mod server;
-mod chess;
+use std::env;
+use std::time::Duration;
 
 use actix::Actor;
-use lobby::Lobby;
-use chess::ChessWish;
+use chessmate::chess::{computer, uci, ChessGame};
+use chessmate::{runtime, server};
 
 #[actix_rt::main]
 async fn main() -> std::io::Result<()> {
+    env_logger::init();
 
-    let game_server = runtime::GameServer::<ChessWish, Lobby<ChessWish>>::default().start();
+    // seconds tickets wait for a pair, `0` to wait until cancelled
+    let ticket_ttl = match env::var("TICKET_TTL").map(|x| x.parse::<u64>()) {
+        Ok(Ok(0)) => None,
+        Ok(Ok(secs)) => Some(Duration::from_secs(secs)),
+        Ok(Err(_)) => {
+            log::error!("invalid ticket TTL, default one is used");
+            Some(runtime::DEFAULT_TICKET_TTL)
+        }
+        Err(_) => Some(runtime::DEFAULT_TICKET_TTL),
+    };
+    let game_server =
+        runtime::DefaultGameServer::<ChessGame>::with_ticket_ttl(ticket_ttl)
+            .start();
+    // milliseconds computers think over a move at most
+    let budget = match env::var("COMPUTER_BUDGET") {
+        Ok(budget) => computer::parse_budget(&budget).unwrap_or_else(|| {
+            log::error!("invalid computer budget, default one is used");
+            computer::DEFAULT_BUDGET
+        }),
+        Err(_) => computer::DEFAULT_BUDGET,
+    };
+    computer::start_computers(&game_server, budget);
+    // external engines as `name=path` separated by `;`
+    let engines = env::var("UCI_ENGINES").unwrap_or_default();
+    for engine in engines.split(';').filter(|x| !x.is_empty()) {
+        match engine.parse() {
+            Ok(config) => match uci::start_engine(&game_server, config) {
+                Ok(name) => log::info!("started engine {}", name),
+                Err(err) => log::error!("can't start {}: {}", engine, err),
+            },
+            Err(_) => log::error!("invalid engine config: {}", engine),
+        }
+    }
 
     server::run_server(game_server).await
 }
@@ -1,26 +1,105 @@
 use crate::core::{GameId, UserId};
-use crate::domain::{PairObserver, Wish};
+use crate::domain::{
+    ActionResult, CancelReason, CancelTicketError, Game, PairObserver,
+    PlayerCommand, SetTicketError, Wish,
+};
+use crate::rating::Rating;
 use actix::{Message, Recipient};
+use std::marker::PhantomData;
 
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct NewGame(pub GameId);
 
+/// Ticket left the lobby without a pair for given reason
 #[derive(Message)]
 #[rtype(result = "()")]
+pub struct TicketCancelled(pub CancelReason);
+
+#[derive(Message)]
+#[rtype(result = "Result<(), SetTicketError>")]
 pub struct FindPair<W: Wish> {
     pub user_id: UserId,
     pub wish: W,
     pub addr: Recipient<NewGame>,
+    pub cancel_addr: Recipient<TicketCancelled>,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), CancelTicketError>")]
+pub struct CancelTicket {
+    pub user_id: UserId,
+}
+
+#[derive(Message)]
+#[rtype(result = "ActionResult<G>")]
+pub struct PlayerAction<G: Game> {
+    pub user_id: UserId,
+    pub command: PlayerCommand<G::Action>,
+}
+
+#[derive(Message)]
+#[rtype(result = "Option<(GameId, String)>")]
+pub struct GetState {
+    pub user_id: UserId,
+}
+
+#[derive(Message)]
+#[rtype(result = "Option<String>")]
+pub struct GetReplay {
+    pub game: GameId,
+}
+
+/// Request of user's ratings in every category `C` of games
+#[derive(Message)]
+#[rtype(result = "Vec<(C, Rating)>")]
+pub struct GetRatings<C: 'static> {
+    pub user_id: UserId,
+    pub category: PhantomData<C>,
+}
+
+/// Event in the game pushed to player
+/// * `Side` - side player got in the game which just started
+/// * `Opponent` - opponent performed a command
+/// * `Clock` - clocks changed, after the game starts and after every move
+/// * `GameOver` - game is finished
+#[derive(Message)]
+#[rtype(result = "()")]
+pub enum GameEvent<G: Game> {
+    Side(G::Side),
+    Opponent(PlayerCommand<G::Action>),
+    Clock(G::Clock),
+    GameOver(G::Outcome),
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Connect<G: Game> {
+    pub user_id: UserId,
+    pub addr: Recipient<GameEvent<G>>,
+}
+
+/// Session of user is closed, `addr` tells it from newer sessions of
+/// the same user
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Disconnect<G: Game> {
+    pub user_id: UserId,
+    pub addr: Recipient<GameEvent<G>>,
 }
 
 #[derive(Debug)]
 pub struct TicketObserver {
     pub feedback: Recipient<NewGame>,
+    pub cancel_feedback: Recipient<TicketCancelled>,
 }
 
 impl PairObserver for TicketObserver {
     fn notify(&self, game: GameId) {
         let _ = self.feedback.do_send(NewGame(game));
     }
+
+    fn cancel(&self, reason: CancelReason) {
+        let _ = self.cancel_feedback.do_send(TicketCancelled(reason));
+    }
 }
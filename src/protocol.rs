@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::core::GameId;
+use crate::domain::CancelReason;
+
+/// Version of the protocol, client frames with other version are rejected
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Frame sent by client to the server
+/// ```json
+/// {"version": 1, "id": 7, "type": "move", "payload": {"action": "e2e4"}}
+/// ```
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ClientFrame {
+    pub version: u32,
+    /// Request id chosen by client, echoed back in the reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub message: ClientMessage,
+}
+
+/// Messages client can send
+/// * `Find` - find pair with given wish, e.g. `white`, `any 3+2` to play
+///   either color with 3 minutes and 2 seconds per move, or
+///   `black computer:5` to play with computer of given level
+/// * `Cancel` - stop looking for a pair
+/// * `Move` - perform game action, e.g. `e2e4` or `Nf3`
+/// * `Resign` - give up the game
+/// * `OfferDraw`, `AcceptDraw`, `DeclineDraw` - draw negotiation
+/// * `ClaimDraw` - claim draw by threefold repetition or fifty-move rule
+/// * `Position` - request current state of the game
+/// * `Leave` - leave the game
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Find { wish: String },
+    Cancel,
+    Move { action: String },
+    Resign,
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    ClaimDraw,
+    Position,
+    Leave,
+}
+
+/// Frame sent by server to the client
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ServerFrame {
+    pub version: u32,
+    /// Id of request this frame is reply to, absent for pushes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub message: ServerMessage,
+}
+
+/// Messages server can send
+/// * `Ok` - request succeeded
+/// * `Error` - request failed, see `ErrorCode` for details
+/// * `NewGame` - pair was found and game was started
+/// * `Side` - side player got in the new game, e.g. `"black"` for chess
+/// * `TicketCancelled` - pair won't be found for `Find` request, e.g.
+///   because it waited for too long
+/// * `Position` - current state of the game
+/// * `Opponent` - opponent sent a message in the game, moves are sent in
+///   canonical form, e.g. `g1f3` for chess
+/// * `Clock` - clocks of timed game changed, state is game specific, e.g.
+///   milliseconds each player has left
+/// * `GameOver` - game is finished with given outcome, e.g. `1-0`, details
+///   are game specific, e.g. winner and termination reason
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Ok,
+    Error {
+        code: ErrorCode,
+        message: String,
+        /// Game specific reason, present for `BadAction`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<Value>,
+    },
+    NewGame {
+        game: GameId,
+    },
+    Side {
+        side: Value,
+    },
+    TicketCancelled {
+        reason: CancelReason,
+    },
+    Position {
+        game: GameId,
+        state: String,
+    },
+    Opponent {
+        message: ClientMessage,
+    },
+    Clock {
+        state: Value,
+    },
+    GameOver {
+        outcome: String,
+        details: Value,
+    },
+}
+
+/// Machine-readable reason of failed request
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    BadFrame,
+    UnsupportedVersion,
+    InvalidWish,
+    InvalidAction,
+    DuplicateTicket,
+    AlreadyPlaying,
+    NoTicket,
+    NotPlaying,
+    NoDrawOffer,
+    BadAction,
+    Internal,
+}
+
+impl ServerFrame {
+    pub fn reply(id: Option<u64>, message: ServerMessage) -> ServerFrame {
+        ServerFrame {
+            version: PROTOCOL_VERSION,
+            id,
+            message,
+        }
+    }
+
+    pub fn push(message: ServerMessage) -> ServerFrame {
+        ServerFrame::reply(None, message)
+    }
+
+    pub fn error(
+        id: Option<u64>,
+        code: ErrorCode,
+        message: impl Into<String>,
+    ) -> ServerFrame {
+        ServerFrame::reply(
+            id,
+            ServerMessage::Error {
+                code,
+                message: message.into(),
+                reason: None,
+            },
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("server frame is serializable")
+    }
+}
+
+/// Reason client frame can't be parsed, with id of request if it was read
+#[derive(Debug)]
+pub enum ParseFrameError {
+    /// Frame is not valid JSON or doesn't match the schema
+    BadFrame(Option<u64>, String),
+    UnsupportedVersion(Option<u64>, u32),
+}
+
+impl ParseFrameError {
+    pub fn to_frame(&self) -> ServerFrame {
+        match self {
+            ParseFrameError::BadFrame(id, err) => {
+                ServerFrame::error(*id, ErrorCode::BadFrame, err.as_str())
+            }
+            ParseFrameError::UnsupportedVersion(id, version) => {
+                ServerFrame::error(
+                    *id,
+                    ErrorCode::UnsupportedVersion,
+                    format!(
+                        "version {} is not supported, use {}",
+                        version, PROTOCOL_VERSION
+                    ),
+                )
+            }
+        }
+    }
+}
+
+impl ClientFrame {
+    pub fn parse(text: &str) -> Result<ClientFrame, ParseFrameError> {
+        let frame =
+            serde_json::from_str::<ClientFrame>(text).map_err(|err| {
+                // try to recover request id, so client can match the error
+                let id = serde_json::from_str::<serde_json::Value>(text)
+                    .ok()
+                    .and_then(|value| {
+                        value.get("id").and_then(|id| id.as_u64())
+                    });
+                ParseFrameError::BadFrame(id, err.to_string())
+            })?;
+        if frame.version != PROTOCOL_VERSION {
+            return Err(ParseFrameError::UnsupportedVersion(
+                frame.id,
+                frame.version,
+            ));
+        }
+        Ok(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_client_frame() {
+        let frame = ClientFrame::parse(
+            r#"{"version": 1, "id": 7, "type": "move", "payload": {"action": "e2e4"}}"#,
+        )
+        .unwrap();
+        assert_eq!(frame.id, Some(7));
+        assert_eq!(
+            frame.message,
+            ClientMessage::Move {
+                action: "e2e4".to_string()
+            }
+        );
+
+        let frame = ClientFrame::parse(
+            r#"{"version": 1, "type": "find", "payload": {"wish": "any 3+2"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            frame.message,
+            ClientMessage::Find {
+                wish: "any 3+2".to_string()
+            }
+        );
+
+        let frame =
+            ClientFrame::parse(r#"{"version": 1, "type": "resign"}"#).unwrap();
+        assert_eq!(frame.id, None);
+        assert_eq!(frame.message, ClientMessage::Resign);
+    }
+
+    #[test]
+    fn test_parse_bad_frame() {
+        assert!(matches!(
+            ClientFrame::parse("/find?white"),
+            Err(ParseFrameError::BadFrame(None, _))
+        ));
+        assert!(matches!(
+            ClientFrame::parse(r#"{"version": 1, "id": 3, "type": "fly"}"#),
+            Err(ParseFrameError::BadFrame(Some(3), _))
+        ));
+        assert!(matches!(
+            ClientFrame::parse(r#"{"version": 2, "id": 4, "type": "leave"}"#),
+            Err(ParseFrameError::UnsupportedVersion(Some(4), 2))
+        ));
+    }
+
+    #[test]
+    fn test_server_frame_json() {
+        let frame = ServerFrame::reply(Some(1), ServerMessage::Ok);
+        assert_eq!(frame.to_json(), r#"{"version":1,"id":1,"type":"ok"}"#);
+
+        let frame = ServerFrame::push(ServerMessage::Opponent {
+            message: ClientMessage::OfferDraw,
+        });
+        assert_eq!(
+            frame.to_json(),
+            r#"{"version":1,"type":"opponent","payload":{"message":{"type":"offer_draw"}}}"#
+        );
+
+        let frame = ServerFrame::push(ServerMessage::TicketCancelled {
+            reason: CancelReason::Expired,
+        });
+        assert_eq!(
+            frame.to_json(),
+            r#"{"version":1,"type":"ticket_cancelled","payload":{"reason":"expired"}}"#
+        );
+
+        let frame = ServerFrame::error(None, ErrorCode::NotPlaying, "oops");
+        assert_eq!(
+            frame.to_json(),
+            r#"{"version":1,"type":"error","payload":{"code":"not_playing","message":"oops"}}"#
+        );
+
+        let frame = ServerFrame::reply(
+            Some(2),
+            ServerMessage::Error {
+                code: ErrorCode::BadAction,
+                message: "it is not your turn".to_string(),
+                reason: Some(
+                    serde_json::json!({"invalid_move": "not_your_turn"}),
+                ),
+            },
+        );
+        assert_eq!(
+            frame.to_json(),
+            r#"{"version":1,"id":2,"type":"error","payload":{"code":"bad_action","message":"it is not your turn","reason":{"invalid_move":"not_your_turn"}}}"#
+        );
+    }
+}
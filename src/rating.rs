@@ -0,0 +1,243 @@
+//! Strength of players in Glicko-2 rating system, see
+//! http://www.glicko.net/glicko/glicko2.pdf, every game is a rating
+//! period of its own
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::hash::Hash;
+
+use serde::Serialize;
+
+use crate::core::UserId;
+use crate::domain::Skill;
+
+// ratio between Glicko and Glicko-2 scales
+const SCALE: f64 = 173.7178;
+// constrains change of volatility over time
+const TAU: f64 = 0.5;
+// precision of volatility
+const EPSILON: f64 = 0.000_001;
+
+/// Rating of new player, who may be of any strength
+pub const DEFAULT_RATING: f64 = 1500.0;
+pub const MAX_DEVIATION: f64 = 350.0;
+pub const DEFAULT_VOLATILITY: f64 = 0.06;
+/// Narrowest range of ratings player looks for opponents in
+pub const MIN_RANGE: f64 = 100.0;
+
+/// Player's rating on Glicko scale with deviation of it, the true
+/// strength is within two deviations from rating with 95% confidence,
+/// and volatility, expected fluctuation of the strength
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Rating {
+        Rating {
+            rating: DEFAULT_RATING,
+            deviation: MAX_DEVIATION,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+}
+
+impl Rating {
+    /// Player looks for opponents within two deviations first, so
+    /// players whose strength is known better get closer opponents
+    pub fn skill(&self) -> Skill {
+        Skill {
+            rating: self.rating,
+            range: (2.0 * self.deviation).max(MIN_RANGE),
+        }
+    }
+
+    fn mu(&self) -> f64 {
+        (self.rating - DEFAULT_RATING) / SCALE
+    }
+
+    fn phi(&self) -> f64 {
+        self.deviation / SCALE
+    }
+
+    /// Rating after rating period with given games
+    /// # Arguments
+    /// * `games` - ratings of opponents and scores player got, 1 for win,
+    ///   0.5 for draw and 0 for loss
+    pub fn update(&self, games: &[(Rating, f64)]) -> Rating {
+        let (mu, phi) = (self.mu(), self.phi());
+        if games.is_empty() {
+            return self.with_phi(
+                mu,
+                phi.hypot(self.volatility),
+                self.volatility,
+            );
+        }
+        let g = |phi: f64| 1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt();
+        let expected = |opponent: &Rating| {
+            let g = g(opponent.phi());
+            (g, 1.0 / (1.0 + (-g * (mu - opponent.mu())).exp()))
+        };
+        let mut variance = 0.0;
+        let mut improvement = 0.0;
+        for (opponent, score) in games {
+            let (g, e) = expected(opponent);
+            variance += g * g * e * (1.0 - e);
+            improvement += g * (score - e);
+        }
+        let variance = 1.0 / variance;
+        let delta = variance * improvement;
+        let volatility = self.next_volatility(delta, variance);
+        let phi_star = phi.hypot(volatility);
+        let phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / variance).sqrt();
+        self.with_phi(mu + phi * phi * improvement, phi, volatility)
+    }
+
+    /// Solves equation for new volatility with Illinois algorithm
+    fn next_volatility(&self, delta: f64, variance: f64) -> f64 {
+        let phi = self.phi();
+        let a = (self.volatility * self.volatility).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            let d = phi * phi + variance + ex;
+            ex * (delta * delta - d) / (2.0 * d * d) - (x - a) / (TAU * TAU)
+        };
+        let mut low = a;
+        let mut high = if delta * delta > phi * phi + variance {
+            (delta * delta - phi * phi - variance).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * TAU) < 0.0 {
+                k += 1.0;
+            }
+            a - k * TAU
+        };
+        let (mut f_low, mut f_high) = (f(low), f(high));
+        while (high - low).abs() > EPSILON {
+            let next = low + (low - high) * f_low / (f_high - f_low);
+            let f_next = f(next);
+            if f_next * f_high <= 0.0 {
+                low = high;
+                f_low = f_high;
+            } else {
+                f_low /= 2.0;
+            }
+            high = next;
+            f_high = f_next;
+        }
+        (low / 2.0).exp()
+    }
+
+    fn with_phi(&self, mu: f64, phi: f64, volatility: f64) -> Rating {
+        Rating {
+            rating: mu * SCALE + DEFAULT_RATING,
+            deviation: (phi * SCALE).min(MAX_DEVIATION),
+            volatility,
+        }
+    }
+}
+
+/// Ratings of users, kept separately for every category of games, e.g.
+/// blitz and classical in chess
+pub struct Ratings<C: Copy + Eq + Hash> {
+    ratings: HashMap<(UserId, C), Rating>,
+}
+
+impl<C: Copy + Eq + Hash> Ratings<C> {
+    pub fn new() -> Ratings<C> {
+        Ratings {
+            ratings: HashMap::new(),
+        }
+    }
+
+    /// Rating of user in category, default one if user hasn't played yet
+    pub fn get(&self, user: UserId, category: C) -> Rating {
+        self.ratings
+            .get(&(user, category))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Ratings of user in every category played
+    pub fn of_user(&self, user: UserId) -> Vec<(C, Rating)> {
+        self.ratings
+            .iter()
+            .filter(|((x, _), _)| *x == user)
+            .map(|((_, category), rating)| (*category, *rating))
+            .collect()
+    }
+
+    /// Updates ratings of both players after the game
+    /// # Arguments
+    /// * `users` - players of the game
+    /// * `score` - score of the first player, 1 for win, 0.5 for draw and
+    ///   0 for loss
+    pub fn record(&mut self, category: C, users: [UserId; 2], score: f64) {
+        let [first, second] = users;
+        let (rating1, rating2) =
+            (self.get(first, category), self.get(second, category));
+        self.ratings
+            .insert((first, category), rating1.update(&[(rating2, score)]));
+        self.ratings.insert(
+            (second, category),
+            rating2.update(&[(rating1, 1.0 - score)]),
+        );
+    }
+}
+
+impl<C: Copy + Eq + Hash> Default for Ratings<C> {
+    fn default() -> Ratings<C> {
+        Ratings::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rating: f64, deviation: f64) -> Rating {
+        Rating {
+            rating,
+            deviation,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+
+    #[test]
+    fn test_update() {
+        // example from the description of the system
+        let player = rating(1500.0, 200.0).update(&[
+            (rating(1400.0, 30.0), 1.0),
+            (rating(1550.0, 100.0), 0.0),
+            (rating(1700.0, 300.0), 0.0),
+        ]);
+        assert!((player.rating - 1464.06).abs() < 0.01, "{:?}", player);
+        assert!((player.deviation - 151.52).abs() < 0.01, "{:?}", player);
+        assert!(
+            (player.volatility - 0.05999).abs() < 0.00001,
+            "{:?}",
+            player
+        );
+
+        let idle = rating(1500.0, 50.0).update(&[]);
+        assert_eq!(idle.rating, 1500.0);
+        assert!(idle.deviation > 50.0);
+        assert_eq!(Rating::default().update(&[]).deviation, MAX_DEVIATION);
+    }
+
+    #[test]
+    fn test_ratings() {
+        let mut ratings = Ratings::new();
+        ratings.record('b', [1, 2], 1.0);
+        ratings.record('b', [1, 3], 0.5);
+        assert!(ratings.get(1, 'b').rating > DEFAULT_RATING);
+        assert!(ratings.get(2, 'b').rating < DEFAULT_RATING);
+        assert!(ratings.get(1, 'b').deviation < MAX_DEVIATION);
+        assert_eq!(ratings.get(1, 'c'), Rating::default());
+        assert_eq!(ratings.of_user(2).len(), 1);
+        assert!(ratings.of_user(4).is_empty());
+    }
+}
@@ -1,42 +1,387 @@
-use crate::domain::{AbstractLobby, Wish, PairObserver};
+use crate::bot::BOT_USER_IDS;
+use crate::core::{GameId, UserId};
+use crate::domain::{
+    AbstractGamePool, AbstractLobby, ActionResult, CancelReason,
+    CancelTicketError, Game, Pair, PlayerCommand, SetTicketError, Wish,
+};
+use crate::gamepool::GamePool;
 use crate::lobby::Lobby;
-use crate::observers::{FindPair, TicketObserver};
+use crate::observers::{
+    CancelTicket, Connect, Disconnect, FindPair, GameEvent, GetRatings,
+    GetReplay, GetState, PlayerAction, TicketObserver,
+};
 
-use actix::{Actor, Context, Handler};
+use actix::{
+    Actor, AsyncContext, Context, Handler, MessageResult, Recipient,
+    SpawnHandle,
+};
 
+use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
-//TODO: implement running gamepool
+// how often rating ranges of waiting players widen
+const WIDEN_INTERVAL: Duration = Duration::from_secs(5);
+// how much rating ranges widen every time
+const RANGE_STEP: f64 = 50.0;
+// widest range rating ranges grow to, as wide as the one of new player
+const MAX_RANGE: f64 = 700.0;
 
-pub struct GameServer<W, L: AbstractLobby<W, TicketObserver>>
-where W: Wish, {
+// how long player whose session is closed may take to connect again
+// before the game is abandoned
+const RECONNECT_GRACE: Duration = Duration::from_secs(30);
+
+/// How long tickets wait for a pair by default
+pub const DEFAULT_TICKET_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Game server with default lobby and game pool
+pub type DefaultGameServer<G> =
+    GameServer<G, Lobby<<G as Game>::Wish>, GamePool<G>>;
+
+pub struct GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
     lobby: L,
-    wish: PhantomData<W>,
+    pool: P,
+    sessions: HashMap<UserId, Recipient<GameEvent<G>>>,
+    /// Timers ending timed games when player to move runs out of time
+    flags: HashMap<GameId, SpawnHandle>,
+    /// Timers abandoning games of players who have disconnected
+    abandons: HashMap<UserId, SpawnHandle>,
+    /// How long tickets wait for a pair, `None` if until cancelled
+    ticket_ttl: Option<Duration>,
+    game: PhantomData<G>,
+}
+
+impl<G, L, P> GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
+    /// Server with tickets waiting for a pair for given time, `None` to
+    /// keep them until they are cancelled
+    pub fn with_ticket_ttl(ticket_ttl: Option<Duration>) -> Self {
+        GameServer {
+            ticket_ttl,
+            ..Default::default()
+        }
+    }
+
+    fn start_game(&mut self, pair: Pair<G::Wish>, ctx: &mut Context<Self>) {
+        let [(user1, wish1), (user2, wish2)] = pair.users;
+        let [side1, side2] = pair.sides;
+        self.pool.start_game(
+            pair.game,
+            [(user1, side1), (user2, side2)],
+            [&wish1, &wish2],
+        );
+        self.push(user1, GameEvent::Side(side1));
+        self.push(user2, GameEvent::Side(side2));
+        self.clock_changed(pair.game, ctx);
+        // session may be closed while its ticket was being paired
+        for user in [user1, user2].iter() {
+            if !self.sessions.contains_key(user) {
+                self.disconnected(*user, ctx);
+            }
+        }
+    }
+
+    fn do_action(
+        &mut self,
+        user_id: UserId,
+        command: PlayerCommand<G::Action>,
+        ctx: &mut Context<Self>,
+    ) -> ActionResult<G> {
+        let users = self
+            .pool
+            .playing(user_id)
+            .and_then(|game| self.pool.users(game));
+        let result = self.pool.do_action(user_id, command);
+        if let (Ok((game, command, outcome)), Some(users)) = (&result, users) {
+            for user in users.iter().filter(|x| **x != user_id) {
+                self.push(*user, GameEvent::Opponent(command.clone()));
+            }
+            match outcome {
+                Some(outcome) => {
+                    if let Some(handle) = self.flags.remove(game) {
+                        ctx.cancel_future(handle);
+                    }
+                    for user in users.iter() {
+                        self.push(*user, GameEvent::GameOver(outcome.clone()));
+                    }
+                }
+                None if matches!(command, PlayerCommand::Act(_)) => {
+                    self.clock_changed(*game, ctx)
+                }
+                None => (),
+            }
+        }
+        result
+    }
+
+    /// Gives player who has no session time to connect again, then leaves
+    /// the game for player, so opponent isn't left waiting forever
+    fn disconnected(&mut self, user: UserId, ctx: &mut Context<Self>) {
+        if self.pool.playing(user).is_none() {
+            return;
+        }
+        let handle = ctx.run_later(RECONNECT_GRACE, move |act, ctx| {
+            act.abandons.remove(&user);
+            if !act.sessions.contains_key(&user) {
+                log::info!("{} hasn't connected again, leaving game", user);
+                let _ = act.do_action(user, PlayerCommand::Leave, ctx);
+            }
+        });
+        if let Some(handle) = self.abandons.insert(user, handle) {
+            ctx.cancel_future(handle);
+        }
+    }
+
+    /// Tells players new state of clocks and waits for the flag of player
+    /// to move to fall
+    fn clock_changed(&mut self, game: GameId, ctx: &mut Context<Self>) {
+        if let Some(handle) = self.flags.remove(&game) {
+            ctx.cancel_future(handle);
+        }
+        let (clock, deadline, users) = match (
+            self.pool.clock(game),
+            self.pool.deadline(game),
+            self.pool.users(game),
+        ) {
+            (Some(clock), Some(deadline), Some(users)) => {
+                (clock, deadline, users)
+            }
+            _ => return,
+        };
+        for user in users.iter() {
+            self.push(*user, GameEvent::Clock(clock.clone()));
+        }
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let handle =
+            ctx.run_later(timeout, move |act, ctx| act.flag_fall(game, ctx));
+        self.flags.insert(game, handle);
+    }
+
+    fn flag_fall(&mut self, game: GameId, ctx: &mut Context<Self>) {
+        self.flags.remove(&game);
+        let users = self.pool.users(game);
+        match (self.pool.check_time(game), users) {
+            (Some(outcome), Some(users)) => {
+                for user in users.iter() {
+                    self.push(*user, GameEvent::GameOver(outcome.clone()));
+                }
+            }
+            // timer fired a bit early, or the game is over already
+            _ => self.clock_changed(game, ctx),
+        }
+    }
+
+    fn push(&self, user: UserId, event: GameEvent<G>) {
+        if let Some(session) = self.sessions.get(&user) {
+            let _ = session.do_send(event);
+        }
+    }
 }
 
-impl<W, L> Default for GameServer<W, L>
-where W: Wish,
-      L: AbstractLobby<W, TicketObserver> {
+impl<G, L, P> Default for GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
     fn default() -> Self {
         GameServer {
             lobby: L::new(),
-            wish: PhantomData,
+            pool: P::new(),
+            sessions: HashMap::new(),
+            flags: HashMap::new(),
+            abandons: HashMap::new(),
+            ticket_ttl: Some(DEFAULT_TICKET_TTL),
+            game: PhantomData,
         }
     }
 }
 
-impl<W, L> Handler<FindPair<W>> for GameServer<W, L> 
-where W: Wish,
-      L: AbstractLobby<W, TicketObserver> {
+impl<G, L, P> Handler<FindPair<G::Wish>> for GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
+    type Result = Result<(), SetTicketError>;
+    fn handle(
+        &mut self,
+        msg: FindPair<G::Wish>,
+        ctx: &mut Context<Self>,
+    ) -> Self::Result {
+        let observer = TicketObserver {
+            feedback: msg.addr,
+            cancel_feedback: msg.cancel_addr,
+        };
+        // bots aren't rated, they play anyone
+        let skill = if msg.user_id >= BOT_USER_IDS {
+            None
+        } else {
+            let category = msg.wish.category();
+            Some(self.pool.rating(msg.user_id, category).skill())
+        };
+        let added = if self.pool.playing(msg.user_id).is_some() {
+            Err(SetTicketError::AlreadyPlaying)
+        } else {
+            self.lobby
+                .add_ticket(msg.user_id, msg.wish, skill, observer)
+        };
+        match added {
+            Ok(Some(pair)) => {
+                self.start_game(pair, ctx);
+                Ok(())
+            }
+            Ok(None) => Ok(()),
+            Err(err) => {
+                log::warn!("Can't add ticket of {}: {:?}", msg.user_id, err);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<G, L, P> Handler<PlayerAction<G>> for GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
+    type Result = ActionResult<G>;
+    fn handle(
+        &mut self,
+        msg: PlayerAction<G>,
+        ctx: &mut Context<Self>,
+    ) -> ActionResult<G> {
+        log::debug!("Got command {:?} from {}", msg.command, msg.user_id);
+        self.do_action(msg.user_id, msg.command, ctx)
+    }
+}
+
+impl<G, L, P> Handler<GetState> for GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
+    type Result = Option<(GameId, String)>;
+    fn handle(&mut self, msg: GetState, _: &mut Context<Self>) -> Self::Result {
+        self.pool.state(msg.user_id)
+    }
+}
+
+impl<G, L, P> Handler<GetReplay> for GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
+    type Result = Option<String>;
+    fn handle(
+        &mut self,
+        msg: GetReplay,
+        _: &mut Context<Self>,
+    ) -> Self::Result {
+        self.pool.replay(msg.game)
+    }
+}
+
+impl<G, L, P> Handler<GetRatings<G::Category>> for GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
+    type Result = MessageResult<GetRatings<G::Category>>;
+    fn handle(
+        &mut self,
+        msg: GetRatings<G::Category>,
+        _: &mut Context<Self>,
+    ) -> Self::Result {
+        MessageResult(self.pool.ratings(msg.user_id))
+    }
+}
+
+impl<G, L, P> Handler<Connect<G>> for GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
     type Result = ();
-    fn handle(&mut self, msg: FindPair<W>, _: &mut Context<Self>) {
-        let observer = TicketObserver { feedback: msg.addr };
-        self.lobby.add_ticket(msg.user_id, msg.wish, observer);
+    fn handle(&mut self, msg: Connect<G>, ctx: &mut Context<Self>) {
+        if let Some(handle) = self.abandons.remove(&msg.user_id) {
+            ctx.cancel_future(handle);
+        }
+        self.sessions.insert(msg.user_id, msg.addr);
     }
 }
 
-impl<W, L> Actor for GameServer<W, L> 
-where W: Wish,
-      L: AbstractLobby<W, TicketObserver> {
+impl<G, L, P> Handler<Disconnect<G>> for GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
+    type Result = ();
+    fn handle(&mut self, msg: Disconnect<G>, ctx: &mut Context<Self>) {
+        // user has connected again, the new session stays
+        if self.sessions.get(&msg.user_id) != Some(&msg.addr) {
+            return;
+        }
+        self.sessions.remove(&msg.user_id);
+        // user has no session to be told about the game, so the game
+        // of paired ticket would never be played
+        let _ = self
+            .lobby
+            .remove_ticket(msg.user_id, CancelReason::Disconnected);
+        self.disconnected(msg.user_id, ctx);
+    }
+}
+
+impl<G, L, P> Handler<CancelTicket> for GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
+    type Result = Result<(), CancelTicketError>;
+    fn handle(
+        &mut self,
+        msg: CancelTicket,
+        _: &mut Context<Self>,
+    ) -> Self::Result {
+        self.lobby
+            .remove_ticket(msg.user_id, CancelReason::Cancelled)
+    }
+}
+
+impl<G, L, P> Actor for GameServer<G, L, P>
+where
+    G: Game,
+    L: AbstractLobby<G::Wish, TicketObserver>,
+    P: AbstractGamePool<G>,
+{
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(WIDEN_INTERVAL, |act, ctx| {
+            if let Some(ttl) = act.ticket_ttl {
+                for user in act.lobby.expire(ttl, Instant::now()) {
+                    log::info!("Ticket of {} expired", user);
+                }
+            }
+            for pair in act.lobby.widen(RANGE_STEP, MAX_RANGE) {
+                act.start_game(pair, ctx);
+            }
+        });
+    }
 }
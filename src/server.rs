@@ -4,19 +4,14 @@ use actix_files::NamedFile;
 use actix_web::middleware::Logger;
 use actix_web::{get, web, App, HttpServer, Responder};
 
-use crate::handlers::new_session;
-use crate::runtime::GameServer;
+use crate::handlers::{new_session, ratings, replay};
+use crate::runtime::DefaultGameServer;
 
-use crate::chess::{ChessGame, ChessWish};
-
-use crate::lobby::Lobby;
-use crate::observers::TicketObserver;
+use crate::chess::ChessGame;
 
 pub async fn run_server(
-    listener: Addr<GameServer<ChessWish, Lobby<ChessWish>>>,
+    listener: Addr<DefaultGameServer<ChessGame>>,
 ) -> std::io::Result<()> {
-    env_logger::init();
-
     log::info!("starting server");
     let game_listener = web::Data::new(listener);
     HttpServer::new(move || {
@@ -28,6 +23,14 @@ pub async fn run_server(
                 web::resource("/api/chess/new_session/{user_id}")
                     .to(new_session::<ChessGame>),
             )
+            .service(
+                web::resource("/api/chess/replay/{game_id}")
+                    .to(replay::<ChessGame>),
+            )
+            .service(
+                web::resource("/api/chess/ratings/{user_id}")
+                    .to(ratings::<ChessGame>),
+            )
             .service(fs::Files::new("/static", "./static"))
     })
     .bind("127.0.0.1:8000")?
@@ -1,5 +1,9 @@
+const PROTOCOL_VERSION = 1
+
 let choice = null
 let login_id = null
+let socket = null
+let request_id = 0
 
 const login = () => {
     let login_text = document.getElementById("login_input");
@@ -23,12 +27,22 @@ const chose_white = () => {
     console.log(choice);
 }
 
+const chose_any = () => {
+    let placeholder = document.getElementById("choice_placeholder");
+    choice = "any";
+    placeholder.textContent = choice;
+    console.log(choice);
+}
+
 const find_pair = () => {
     let result_placeholder = document.getElementById("result");
-    const socket = new WebSocket(`ws://localhost:8000/api/chess/new_session/${login_id}`)
+    socket = new WebSocket(`ws://localhost:8000/api/chess/new_session/${login_id}`)
     socket.addEventListener('open', _event => {
         console.log(_event);
-        socket.send(`/find?${choice}`);
+        let opponent = document.getElementById("opponent_input").value.trim();
+        let time_control = document.getElementById("time_control_input").value;
+        let wish = [choice, opponent, time_control].filter(x => x !== "").join(" ");
+        send("find", { wish: wish });
     })
     socket.addEventListener('message', (msg) => {
         console.log("Message from server: ", msg.data);
@@ -36,16 +50,47 @@ const find_pair = () => {
     })
 }
 
+const send = (type, payload) => {
+    if (socket !== null) {
+        request_id += 1;
+        let frame = { version: PROTOCOL_VERSION, id: request_id, type: type };
+        if (payload !== undefined) {
+            frame.payload = payload;
+        }
+        socket.send(JSON.stringify(frame));
+    }
+}
+
+const make_move = () => {
+    let move_text = document.getElementById("move_input");
+    send("move", { action: move_text.value });
+}
 
 const main = () => {
     document.getElementById("white")
         .addEventListener("click", chose_white);
     document.getElementById("black")
         .addEventListener("click", chose_black)
+    document.getElementById("any")
+        .addEventListener("click", chose_any)
     document.getElementById("find")
         .addEventListener("click", find_pair)
     document.getElementById("login")
         .addEventListener("click", login)
+    document.getElementById("move")
+        .addEventListener("click", make_move)
+    document.getElementById("position")
+        .addEventListener("click", () => send("position"))
+    document.getElementById("offer_draw")
+        .addEventListener("click", () => send("offer_draw"))
+    document.getElementById("accept_draw")
+        .addEventListener("click", () => send("accept_draw"))
+    document.getElementById("claim_draw")
+        .addEventListener("click", () => send("claim_draw"))
+    document.getElementById("resign")
+        .addEventListener("click", () => send("resign"))
+    document.getElementById("cancel")
+        .addEventListener("click", () => send("cancel"))
 }
 
 main()
@@ -0,0 +1,95 @@
+use chessmate::chess::engine::BoardState;
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const KIWIPETE: &str =
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const ENDGAME: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const PROMOTIONS: &str =
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const BUGGY: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+
+fn perft(fen: &str, depth: u32) -> u64 {
+    let mut board = BoardState::new();
+    board.parse_fen(fen).unwrap();
+    board.perft(depth)
+}
+
+#[test]
+fn test_perft_start() {
+    assert_eq!(perft(START, 1), 20);
+    assert_eq!(perft(START, 2), 400);
+    assert_eq!(perft(START, 3), 8902);
+}
+
+#[test]
+fn test_perft_kiwipete() {
+    assert_eq!(perft(KIWIPETE, 1), 48);
+    assert_eq!(perft(KIWIPETE, 2), 2039);
+    assert_eq!(perft(KIWIPETE, 3), 97862);
+}
+
+#[test]
+fn test_perft_endgame() {
+    assert_eq!(perft(ENDGAME, 1), 14);
+    assert_eq!(perft(ENDGAME, 2), 191);
+    assert_eq!(perft(ENDGAME, 3), 2812);
+    assert_eq!(perft(ENDGAME, 4), 43238);
+}
+
+#[test]
+fn test_perft_promotions() {
+    assert_eq!(perft(PROMOTIONS, 1), 6);
+    assert_eq!(perft(PROMOTIONS, 2), 264);
+    assert_eq!(perft(PROMOTIONS, 3), 9467);
+}
+
+#[test]
+fn test_perft_buggy() {
+    assert_eq!(perft(BUGGY, 1), 44);
+    assert_eq!(perft(BUGGY, 2), 1486);
+    assert_eq!(perft(BUGGY, 3), 62379);
+}
+
+#[test]
+fn test_perft_en_passant() {
+    // en passant capture would expose king to the rook
+    assert_eq!(perft("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1", 1), 6);
+    // en passant capture is the only way out of check
+    assert_eq!(perft("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1", 1), 9);
+}
+
+#[test]
+fn test_perft_castling() {
+    assert_eq!(perft("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", 1), 26);
+    assert_eq!(perft("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", 3), 13744);
+}
+
+// deep positions take a while in debug build, run them with
+// `cargo test --release -- --ignored`
+#[test]
+#[ignore]
+fn test_perft_deep() {
+    assert_eq!(perft(START, 5), 4865609);
+    assert_eq!(perft(KIWIPETE, 4), 4085603);
+    assert_eq!(perft(ENDGAME, 6), 11030083);
+    assert_eq!(perft(PROMOTIONS, 5), 15833292);
+    assert_eq!(perft(BUGGY, 4), 2103487);
+    assert_eq!(perft("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 6), 1134888);
+    assert_eq!(perft("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6), 1440467);
+    assert_eq!(
+        perft("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 4),
+        1274206
+    );
+    assert_eq!(perft("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1", 5), 3605103);
+}
+
+#[test]
+fn test_divide() {
+    let board = BoardState::new();
+    let divide = board.divide(2);
+    assert_eq!(divide.len(), 20);
+    assert!(divide.iter().all(|(_, count)| *count == 20));
+    let moves: Vec<String> =
+        divide.iter().map(|(x, _)| x.to_string()).collect();
+    assert!(moves.contains(&"g1f3".to_string()));
+}
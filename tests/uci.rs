@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chessmate::chess::clock::ClockState;
+use chessmate::chess::engine::{BoardState, Color, Limits};
+use chessmate::chess::uci::{UciConfig, UciProcess};
+
+fn config() -> UciConfig {
+    UciConfig {
+        name: "chessmate".to_string(),
+        path: PathBuf::from(env!("CARGO_BIN_EXE_uci")),
+        options: vec![("Hash".to_string(), "8".to_string())],
+        limits: Limits {
+            depth: Some(3),
+            time: Some(Duration::from_secs(1)),
+        },
+    }
+}
+
+fn is_legal(moves: &[String], best: &str) -> bool {
+    let mut board = BoardState::new();
+    for x in moves {
+        let x = board.parse_uci(x).unwrap();
+        board.make_move(x);
+    }
+    board
+        .parse_uci(best)
+        .is_ok_and(|x| board.move_list().contains(&x))
+}
+
+#[test]
+fn test_builtin_engine() {
+    let config = config();
+    let mut process = UciProcess::spawn(&config).unwrap();
+    assert_eq!(process.name(), Some("chessmate"));
+    process.new_game().unwrap();
+
+    let mut moves = vec!["e2e4".to_string(), "e7e5".to_string()];
+    let best = process.go(&moves, config.limits, None, |_| ()).unwrap();
+    let best = best.unwrap();
+    assert!(is_legal(&moves, &best), "{}", best);
+
+    moves.push(best);
+    let clock = ClockState {
+        white: 60_000,
+        black: 60_000,
+        turn: Color::Black,
+        increment: 1_000,
+    };
+    let best = process
+        .go(&moves, config.limits, Some(&clock), |_| ())
+        .unwrap()
+        .unwrap();
+    assert!(is_legal(&moves, &best), "{}", best);
+}